@@ -0,0 +1,244 @@
+use super::{http_agent, send_json, Backend, Reply, Usage, DEFAULT_TIMEOUT};
+use crate::config::{env_var, AnthropicConfig};
+use crate::error::{Error, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const API_VERSION: &str = "2023-06-01";
+const MAX_TOKENS: u32 = 1024;
+/// Tool the model is forced to call so its input follows the response schema
+const RESPONSE_TOOL: &str = "respond";
+
+#[derive(Deserialize, Debug)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    usage: MessagesUsage,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct MessagesUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+    /// Arguments of a `tool_use` block
+    #[serde(default)]
+    input: Option<Value>,
+}
+
+/// Talks to the Anthropic Messages API directly, without the `claude` CLI.
+pub struct Anthropic {
+    api_key: String,
+    base_url: String,
+    model: String,
+    timeout: Duration,
+}
+
+impl Anthropic {
+    pub fn new(api_key: &str, model: &str) -> Self {
+        Anthropic {
+            api_key: api_key.to_string(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            model: resolve_model(model).to_string(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Build a backend from `ANTHROPIC_API_KEY`/`ANTHROPIC_BASE_URL`,
+    /// falling back to the `[anthropic]` config section.
+    pub fn from_config(config: &AnthropicConfig, model: &str) -> Result<Self> {
+        let api_key = env_var("ANTHROPIC_API_KEY")
+            .or_else(|| config.api_key.clone())
+            .ok_or_else(|| {
+                Error::Config(
+                    "no Anthropic API key: set ANTHROPIC_API_KEY or anthropic.api_key".to_string(),
+                )
+            })?;
+        let base_url = env_var("ANTHROPIC_BASE_URL")
+            .or_else(|| config.base_url.clone())
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+
+        Ok(Anthropic::new(&api_key, model).with_base_url(&base_url))
+    }
+
+    /// Post `prompt` to the Messages API. With a `schema`, the model must
+    /// answer by calling a tool whose input is that schema.
+    fn send(&self, prompt: &str, schema: Option<&Value>) -> Result<Reply> {
+        let mut body = json!({
+            "model": self.model,
+            "max_tokens": MAX_TOKENS,
+            "messages": [{"role": "user", "content": prompt}],
+        });
+        if let Some(schema) = schema {
+            body["tools"] = json!([{
+                "name": RESPONSE_TOOL,
+                "description": "Return the answer as structured data",
+                "input_schema": schema,
+            }]);
+            body["tool_choice"] = json!({"type": "tool", "name": RESPONSE_TOOL});
+        }
+
+        let start = Instant::now();
+        let request = http_agent(self.timeout)
+            .post(&format!("{}/v1/messages", self.base_url))
+            .set("x-api-key", &self.api_key)
+            .set("anthropic-version", API_VERSION);
+        let text = send_json(request, body, self.timeout)?;
+
+        let response: MessagesResponse = serde_json::from_str(&text)?;
+        let tool_input = response
+            .content
+            .iter()
+            .find(|block| block.kind == "tool_use")
+            .and_then(|block| block.input.as_ref());
+        let text = match tool_input {
+            Some(input) => input.to_string(),
+            None => response
+                .content
+                .iter()
+                .filter(|block| block.kind == "text")
+                .map(|block| block.text.as_str())
+                .collect(),
+        };
+
+        let mut metadata = self.metadata();
+        metadata.usage = Some(Usage {
+            input_tokens: response.usage.input_tokens,
+            output_tokens: response.usage.output_tokens,
+            cost_usd: None,
+            duration_ms: start.elapsed().as_millis() as u64,
+        });
+        Ok(Reply { text, metadata })
+    }
+}
+
+/// Map the CLI's short model aliases to API model names.
+fn resolve_model(model: &str) -> &str {
+    match model {
+        "haiku" => "claude-haiku-4-5",
+        "sonnet" => "claude-sonnet-4-5",
+        "opus" => "claude-opus-4-1",
+        other => other,
+    }
+}
+
+impl Backend for Anthropic {
+    fn name(&self) -> &str {
+        "anthropic"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn query(&self, prompt: &str) -> Result<Reply> {
+        self.send(prompt, None)
+    }
+
+    fn query_structured(&self, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.send(prompt, Some(schema))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::stub::StubServer;
+
+    #[test]
+    fn resolves_model_aliases() {
+        assert_eq!(Anthropic::new("k", "haiku").model(), "claude-haiku-4-5");
+        assert_eq!(Anthropic::new("k", "sonnet").model(), "claude-sonnet-4-5");
+        assert_eq!(
+            Anthropic::new("k", "claude-3-7-sonnet-latest").model(),
+            "claude-3-7-sonnet-latest"
+        );
+    }
+
+    #[test]
+    fn base_url_trailing_slash_trimmed() {
+        let backend = Anthropic::new("k", "haiku").with_base_url("http://localhost:9000/");
+        assert_eq!(backend.base_url, "http://localhost:9000");
+    }
+
+    #[test]
+    fn query_sends_messages_request() {
+        let server = StubServer::respond(
+            200,
+            r#"{"content": [{"type": "text", "text": "```json\n{\"ok\": true}\n```"}], "usage": {"input_tokens": 12, "output_tokens": 7}}"#,
+        );
+        let backend = Anthropic::new("sk-test", "haiku").with_base_url(&server.url);
+        let reply = backend.query("hello").unwrap();
+        assert_eq!(reply.text, "```json\n{\"ok\": true}\n```");
+        assert_eq!(reply.metadata.backend, "anthropic");
+        assert_eq!(reply.metadata.model, "claude-haiku-4-5");
+        let usage = reply.metadata.usage.unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (12, 7));
+
+        let request = server.request();
+        assert!(request.starts_with("POST /v1/messages"));
+        assert!(request.contains("x-api-key: sk-test"));
+        assert!(request.contains("anthropic-version: 2023-06-01"));
+        assert!(request.contains(r#""content":"hello""#));
+    }
+
+    #[test]
+    fn structured_query_forces_tool() {
+        let server = StubServer::respond(
+            200,
+            r#"{"content": [{"type": "tool_use", "id": "t1", "name": "respond", "input": {"ok": true}}]}"#,
+        );
+        let backend = Anthropic::new("sk-test", "haiku").with_base_url(&server.url);
+        let schema = json!({"type": "object", "properties": {"ok": {"type": "boolean"}}});
+        let reply = backend.query_structured("hello", &schema).unwrap();
+        assert_eq!(reply.text, r#"{"ok":true}"#);
+
+        let request = server.request();
+        assert!(request.contains(r#""tool_choice":{"name":"respond","type":"tool"}"#));
+        assert!(request.contains(r#""input_schema":{"properties""#));
+    }
+
+    #[test]
+    fn api_error_status_and_message() {
+        let server = StubServer::respond(
+            401,
+            r#"{"type": "error", "error": {"type": "authentication_error", "message": "invalid x-api-key"}}"#,
+        );
+        let backend = Anthropic::new("bad", "haiku").with_base_url(&server.url);
+        let err = backend.query("hello").unwrap_err();
+        server.request();
+        match err {
+            Error::Api { status, message } => {
+                assert_eq!(status, 401);
+                assert_eq!(message, "invalid x-api-key");
+            }
+            other => panic!("expected Error::Api, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unreachable_server_is_http_error() {
+        let backend = Anthropic::new("k", "haiku").with_base_url("http://127.0.0.1:1");
+        assert!(matches!(backend.query("hello"), Err(Error::Http(_))));
+    }
+}
@@ -0,0 +1,378 @@
+use super::{Backend, Reply, Usage, DEFAULT_TIMEOUT};
+use crate::config::ClaudeConfig;
+use crate::error::{Error, Result};
+use crate::log;
+use crate::process;
+use serde::Deserialize;
+use serde_json::Value;
+use std::io;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const DEFAULT_BIN: &str = "claude";
+
+#[derive(Deserialize, Debug)]
+pub struct ClaudeResponse {
+    #[serde(default)]
+    pub result: String,
+    /// The reply as JSON when `--json-schema` was given
+    #[serde(default)]
+    pub structured_output: Option<Value>,
+    #[serde(default)]
+    pub session_id: String,
+    #[serde(default)]
+    pub is_error: bool,
+    #[serde(default)]
+    pub total_cost_usd: Option<f64>,
+    #[serde(default)]
+    pub usage: Option<ClaudeUsage>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct ClaudeUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+}
+
+pub struct Claude {
+    model: String,
+    timeout: Duration,
+    bin: String,
+    extra_args: Vec<String>,
+}
+
+impl Claude {
+    pub fn new(model: &str) -> Self {
+        Claude {
+            model: model.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            bin: DEFAULT_BIN.to_string(),
+            extra_args: Vec::new(),
+        }
+    }
+
+    /// Build from the `[claude]` config section. `env_bin`, the value of
+    /// `RGX_CLAUDE_BIN`, takes precedence over `claude.bin`.
+    pub fn from_config(config: &ClaudeConfig, model: &str, env_bin: Option<String>) -> Self {
+        let bin = env_bin
+            .or_else(|| config.bin.clone())
+            .unwrap_or_else(|| DEFAULT_BIN.to_string());
+        Claude::new(model)
+            .with_bin(&bin)
+            .with_args(config.args.clone())
+    }
+
+    /// Run `bin` instead of `claude`: a wrapper script, a pinned version or
+    /// a stand-in for tests.
+    pub fn with_bin(mut self, bin: &str) -> Self {
+        self.bin = bin.to_string();
+        self
+    }
+
+    /// Pass `args` before rgx's own on every invocation.
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.extra_args = args;
+        self
+    }
+
+    /// Kill the CLI if it has not answered within `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn args<'a>(
+        &'a self,
+        prompt: &'a str,
+        resume: Option<&'a str>,
+        schema: Option<&'a str>,
+    ) -> Vec<&'a str> {
+        let mut args: Vec<&str> = self.extra_args.iter().map(String::as_str).collect();
+        args.extend([
+            "-p",
+            prompt,
+            "--model",
+            &self.model,
+            "--output-format",
+            "json",
+        ]);
+        if let Some(session_id) = resume {
+            args.extend(["--resume", session_id]);
+        }
+        if let Some(schema) = schema {
+            args.extend(["--json-schema", schema]);
+        }
+        args
+    }
+
+    fn run(&self, prompt: &str, resume: Option<&str>, schema: Option<&Value>) -> Result<Reply> {
+        let schema = schema.map(Value::to_string);
+        let start = Instant::now();
+        let output = process::output(
+            Command::new(&self.bin).args(self.args(prompt, resume, schema.as_deref())),
+            self.timeout,
+        )
+        .map_err(|err| match err {
+            Error::Io(e) if e.kind() == io::ErrorKind::NotFound => Error::Claude(format!(
+                "cannot run `{}`: {}; install the claude CLI or set --claude-bin/RGX_CLAUDE_BIN",
+                self.bin, e
+            )),
+            err => err,
+        })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::Claude(stderr.to_string()));
+        }
+        log::debug(format_args!(
+            "raw output:\n{}",
+            String::from_utf8_lossy(&output.stdout)
+        ));
+
+        let response: ClaudeResponse = serde_json::from_slice(&output.stdout)?;
+
+        if response.is_error {
+            return Err(Error::Claude(response.result));
+        }
+
+        let mut metadata = self.metadata();
+        if !response.session_id.is_empty() {
+            metadata.session_id = Some(response.session_id);
+        }
+        let usage = response.usage.unwrap_or_default();
+        metadata.usage = Some(Usage {
+            // Prompt caching splits input across three counters
+            input_tokens: usage.input_tokens
+                + usage.cache_creation_input_tokens
+                + usage.cache_read_input_tokens,
+            output_tokens: usage.output_tokens,
+            cost_usd: response.total_cost_usd,
+            duration_ms: start.elapsed().as_millis() as u64,
+        });
+
+        let text = match response.structured_output {
+            Some(output) => output.to_string(),
+            None => response.result,
+        };
+        Ok(Reply { text, metadata })
+    }
+}
+
+impl Backend for Claude {
+    fn name(&self) -> &str {
+        "claude"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// A wrapper script, another version or extra flags may answer
+    /// differently, so they are part of the identity.
+    fn identity(&self) -> String {
+        let mut parts = vec![self.name(), &self.bin];
+        parts.extend(self.extra_args.iter().map(String::as_str));
+        parts.join("\0")
+    }
+
+    fn query(&self, prompt: &str) -> Result<Reply> {
+        self.run(prompt, None, None)
+    }
+
+    fn resume(&self, session_id: &str, prompt: &str) -> Result<Reply> {
+        self.run(prompt, Some(session_id), None)
+    }
+
+    fn query_structured(&self, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.run(prompt, None, Some(schema))
+    }
+
+    fn resume_structured(&self, session_id: &str, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.run(prompt, Some(session_id), Some(schema))
+    }
+}
+
+impl Default for Claude {
+    fn default() -> Self {
+        Claude::new("haiku")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claude_new_sets_model() {
+        let claude = Claude::new("sonnet");
+        assert_eq!(claude.model, "sonnet");
+    }
+
+    #[test]
+    fn claude_default_uses_haiku() {
+        let claude = Claude::default();
+        assert_eq!(claude.model, "haiku");
+    }
+
+    #[test]
+    fn claude_backend_name() {
+        assert_eq!(Claude::default().name(), "claude");
+    }
+
+    #[test]
+    fn claude_metadata_reports_model() {
+        let metadata = Claude::new("opus").metadata();
+        assert_eq!(metadata.backend, "claude");
+        assert_eq!(metadata.model, "opus");
+        assert!(metadata.session_id.is_none());
+    }
+
+    #[test]
+    fn args_for_new_prompt() {
+        let claude = Claude::new("sonnet");
+        assert_eq!(
+            claude.args("hi", None, None),
+            vec!["-p", "hi", "--model", "sonnet", "--output-format", "json"]
+        );
+    }
+
+    #[test]
+    fn args_for_resumed_session() {
+        let claude = Claude::new("haiku");
+        let args = claude.args("hi", Some("abc-123"), None);
+        assert_eq!(&args[args.len() - 2..], &["--resume", "abc-123"]);
+    }
+
+    #[test]
+    fn args_with_json_schema() {
+        let claude = Claude::new("haiku");
+        let args = claude.args("hi", None, Some(r#"{"type":"object"}"#));
+        assert_eq!(
+            &args[args.len() - 2..],
+            &["--json-schema", r#"{"type":"object"}"#]
+        );
+    }
+
+    #[test]
+    fn passthrough_args_come_first() {
+        let claude =
+            Claude::new("haiku").with_args(vec!["--max-turns".to_string(), "2".to_string()]);
+        assert_eq!(
+            &claude.args("hi", None, None)[..3],
+            &["--max-turns", "2", "-p"]
+        );
+    }
+
+    #[test]
+    fn from_config_uses_bin_and_args() {
+        let config = ClaudeConfig {
+            bin: Some("claude-wrapper".to_string()),
+            args: vec!["--verbose".to_string()],
+        };
+        let claude = Claude::from_config(&config, "opus", None);
+        assert_eq!(claude.bin, "claude-wrapper");
+        assert_eq!(claude.extra_args, vec!["--verbose"]);
+        assert_eq!(claude.model, "opus");
+
+        let claude = Claude::from_config(&config, "opus", Some("claude-env".to_string()));
+        assert_eq!(claude.bin, "claude-env");
+        assert_eq!(
+            Claude::from_config(&ClaudeConfig::default(), "opus", None).bin,
+            DEFAULT_BIN
+        );
+    }
+
+    #[test]
+    fn identity_includes_bin_and_args() {
+        let plain = Claude::new("haiku").identity();
+        assert_ne!(
+            plain,
+            Claude::new("haiku").with_bin("claude-beta").identity()
+        );
+        assert_ne!(
+            plain,
+            Claude::new("haiku")
+                .with_args(vec!["--verbose".to_string()])
+                .identity()
+        );
+        assert_eq!(plain, Claude::new("sonnet").identity());
+    }
+
+    #[test]
+    fn missing_bin_names_the_program() {
+        let err = Claude::new("haiku")
+            .with_bin("rgx-no-such-claude")
+            .query("hi")
+            .unwrap_err();
+        assert!(matches!(err, Error::Claude(_)));
+        assert!(err.to_string().contains("rgx-no-such-claude"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_fake_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("fake-claude.sh");
+        // Echoes its first argument back so the passthrough args are visible
+        std::fs::write(
+            &script,
+            r#"printf '{"result": "%s", "session_id": "fake"}' "$1""#,
+        )
+        .unwrap();
+
+        let claude = Claude::new("haiku").with_bin("sh").with_args(vec![
+            script.to_str().unwrap().to_string(),
+            "--first".to_string(),
+        ]);
+        let reply = claude.query("hi").unwrap();
+        assert_eq!(reply.text, "--first");
+        assert_eq!(reply.metadata.session_id.as_deref(), Some("fake"));
+    }
+
+    #[test]
+    fn parse_claude_response_valid() {
+        let json = r#"{"result": "hello", "session_id": "abc", "is_error": false}"#;
+        let resp: ClaudeResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.result, "hello");
+        assert!(!resp.is_error);
+    }
+
+    #[test]
+    fn parse_claude_response_missing_optional_fields() {
+        // session_id and is_error have #[serde(default)]
+        let json = r#"{"result": "hello"}"#;
+        let resp: ClaudeResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.result, "hello");
+        assert_eq!(resp.session_id, "");
+        assert!(!resp.is_error);
+    }
+
+    #[test]
+    fn parse_claude_response_structured_output() {
+        let json =
+            r#"{"result": "", "structured_output": {"pattern": "\\d+"}, "session_id": "abc"}"#;
+        let resp: ClaudeResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.structured_output.unwrap()["pattern"], "\\d+");
+    }
+
+    #[test]
+    fn parse_claude_response_usage() {
+        let json = r#"{"result": "{}", "total_cost_usd": 0.0123, "usage": {"input_tokens": 4, "cache_creation_input_tokens": 1000, "cache_read_input_tokens": 200, "output_tokens": 56, "service_tier": "standard"}}"#;
+        let resp: ClaudeResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.total_cost_usd, Some(0.0123));
+        let usage = resp.usage.unwrap();
+        assert_eq!(usage.input_tokens, 4);
+        assert_eq!(usage.cache_read_input_tokens, 200);
+        assert_eq!(usage.output_tokens, 56);
+    }
+
+    #[test]
+    fn parse_claude_response_error_flag() {
+        let json = r#"{"result": "error message", "is_error": true}"#;
+        let resp: ClaudeResponse = serde_json::from_str(json).unwrap();
+        assert!(resp.is_error);
+    }
+}
@@ -0,0 +1,99 @@
+use super::{Backend, Reply, Usage};
+use crate::error::{Error, Result};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+/// Deterministic in-process backend that replays canned responses in order
+/// and records every prompt it receives.
+#[derive(Default)]
+pub struct MockBackend {
+    responses: RefCell<VecDeque<String>>,
+    prompts: RefCell<Vec<String>>,
+    resumed: RefCell<Vec<String>>,
+    schemas: RefCell<Vec<Value>>,
+    session_id: Option<String>,
+    usage: Option<Usage>,
+}
+
+impl MockBackend {
+    pub fn new<I, S>(responses: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        MockBackend {
+            responses: RefCell::new(responses.into_iter().map(Into::into).collect()),
+            ..MockBackend::default()
+        }
+    }
+
+    /// Report `session_id` in every reply and accept `resume` calls.
+    pub fn with_session(mut self, session_id: &str) -> Self {
+        self.session_id = Some(session_id.to_string());
+        self
+    }
+
+    /// Report `usage` in every reply.
+    pub fn with_usage(mut self, usage: Usage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    pub fn prompts(&self) -> Vec<String> {
+        self.prompts.borrow().clone()
+    }
+
+    /// Session ids passed to `resume`, in order.
+    pub fn resumed(&self) -> Vec<String> {
+        self.resumed.borrow().clone()
+    }
+
+    /// Schemas passed to the structured query methods, in order.
+    pub fn schemas(&self) -> Vec<Value> {
+        self.schemas.borrow().clone()
+    }
+}
+
+impl Backend for MockBackend {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn model(&self) -> &str {
+        "mock-model"
+    }
+
+    fn query(&self, prompt: &str) -> Result<Reply> {
+        self.prompts.borrow_mut().push(prompt.to_string());
+        let text = self
+            .responses
+            .borrow_mut()
+            .pop_front()
+            .ok_or_else(|| Error::Claude("mock backend has no responses left".to_string()))?;
+        let mut metadata = self.metadata();
+        metadata.session_id = self.session_id.clone();
+        metadata.usage = self.usage.clone();
+        Ok(Reply { text, metadata })
+    }
+
+    fn resume(&self, session_id: &str, prompt: &str) -> Result<Reply> {
+        if self.session_id.is_none() {
+            return Err(Error::Session("mock backend has no sessions".to_string()));
+        }
+        self.resumed.borrow_mut().push(session_id.to_string());
+        self.query(prompt)
+    }
+
+    /// Records the schema separately so prompts stay comparable with
+    /// `build_prompt`.
+    fn query_structured(&self, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.schemas.borrow_mut().push(schema.clone());
+        self.query(prompt)
+    }
+
+    fn resume_structured(&self, session_id: &str, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.schemas.borrow_mut().push(schema.clone());
+        self.resume(session_id, prompt)
+    }
+}
@@ -0,0 +1,308 @@
+pub mod anthropic;
+pub mod claude;
+#[cfg(test)]
+pub mod mock;
+pub mod openai;
+#[cfg(test)]
+pub mod stub;
+
+use crate::error::{Error, Result};
+use crate::log;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+/// How long a single backend call may take before it is abandoned.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Providers rgx can send prompts to.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// The `claude` CLI
+    #[default]
+    Claude,
+    /// The Anthropic Messages API over HTTP
+    Anthropic,
+    /// An OpenAI-compatible chat completions endpoint (Ollama, llama.cpp, ...)
+    Openai,
+}
+
+impl BackendKind {
+    /// Model used when neither the CLI, environment nor config picks one.
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            BackendKind::Claude | BackendKind::Anthropic => "haiku",
+            BackendKind::Openai => openai::DEFAULT_MODEL,
+        }
+    }
+}
+
+/// Details about how a reply was produced, surfaced alongside command output.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    pub backend: String,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Served from the response cache rather than the backend
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+impl Metadata {
+    /// Count the usage of an earlier call, such as a retry's first attempt,
+    /// towards this reply.
+    pub fn add_usage(&mut self, earlier: &Metadata) {
+        if let Some(usage) = &earlier.usage {
+            *self.usage.get_or_insert_with(Usage::default) += usage;
+        }
+    }
+}
+
+/// Tokens, cost and wall time spent answering a prompt.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Only known when the backend reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    pub duration_ms: u64,
+}
+
+impl std::ops::AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost_usd = match (self.cost_usd, other.cost_usd) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or_default() + b.unwrap_or_default()),
+        };
+        self.duration_ms += other.duration_ms;
+    }
+}
+
+/// A model's answer to a single prompt.
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub text: String,
+    pub metadata: Metadata,
+}
+
+/// A provider capable of answering prompts.
+pub trait Backend {
+    /// Short identifier for this backend, e.g. "claude".
+    fn name(&self) -> &str;
+
+    /// Model the backend sends prompts to.
+    fn model(&self) -> &str;
+
+    /// Everything besides the model that decides how the backend answers,
+    /// for telling cached replies apart. Usually just the name.
+    fn identity(&self) -> String {
+        self.name().to_string()
+    }
+
+    fn query(&self, prompt: &str) -> Result<Reply>;
+
+    /// Continue the conversation identified by `session_id` with a follow-up
+    /// prompt. Only backends that keep server-side sessions support this.
+    fn resume(&self, session_id: &str, prompt: &str) -> Result<Reply> {
+        let _ = (session_id, prompt);
+        Err(Error::Session(format!(
+            "the {} backend cannot resume sessions",
+            self.name()
+        )))
+    }
+
+    /// Send `prompt` with the reply constrained to a JSON object matching
+    /// `schema`. Backends without a structured output mode describe the
+    /// schema in the prompt instead.
+    fn query_structured(&self, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.query(&describe_schema(prompt, schema))
+    }
+
+    /// `resume` with the reply constrained to `schema`, as in
+    /// `query_structured`.
+    fn resume_structured(&self, session_id: &str, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.resume(session_id, &describe_schema(prompt, schema))
+    }
+
+    /// Forget the reply `query_structured` gave for `prompt` and `schema`,
+    /// which the caller could not parse, so it is never served again.
+    fn discard(&self, prompt: &str, schema: &Value) {
+        let _ = (prompt, schema);
+    }
+
+    fn metadata(&self) -> Metadata {
+        Metadata {
+            backend: self.name().to_string(),
+            model: self.model().to_string(),
+            session_id: None,
+            cached: false,
+            usage: None,
+        }
+    }
+}
+
+impl<B: Backend + ?Sized> Backend for Box<B> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn model(&self) -> &str {
+        (**self).model()
+    }
+
+    fn identity(&self) -> String {
+        (**self).identity()
+    }
+
+    fn query(&self, prompt: &str) -> Result<Reply> {
+        (**self).query(prompt)
+    }
+
+    fn resume(&self, session_id: &str, prompt: &str) -> Result<Reply> {
+        (**self).resume(session_id, prompt)
+    }
+
+    fn query_structured(&self, prompt: &str, schema: &Value) -> Result<Reply> {
+        (**self).query_structured(prompt, schema)
+    }
+
+    fn resume_structured(&self, session_id: &str, prompt: &str, schema: &Value) -> Result<Reply> {
+        (**self).resume_structured(session_id, prompt, schema)
+    }
+
+    fn discard(&self, prompt: &str, schema: &Value) {
+        (**self).discard(prompt, schema)
+    }
+
+    fn metadata(&self) -> Metadata {
+        (**self).metadata()
+    }
+}
+
+/// `prompt` followed by `schema`, for backends that cannot enforce it.
+pub(crate) fn describe_schema(prompt: &str, schema: &Value) -> String {
+    format!(
+        "{}\n\nRespond with ONLY a JSON object, no markdown, matching this JSON Schema:\n{}",
+        prompt, schema
+    )
+}
+
+/// Error body shared by the Anthropic and OpenAI-compatible APIs.
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorDetail {
+    message: String,
+}
+
+/// HTTP agent that gives up on a request after `timeout`.
+pub(crate) fn http_agent(timeout: Duration) -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(timeout).build()
+}
+
+/// Post `body` and return the raw response text, logging both in debug mode.
+/// Headers are left out so API keys never reach the log.
+pub(crate) fn send_json(request: ureq::Request, body: Value, timeout: Duration) -> Result<String> {
+    log::debug(format_args!(
+        "POST {}\n{}",
+        request.url(),
+        serde_json::to_string_pretty(&body).unwrap_or_default()
+    ));
+    let response = request
+        .send_json(body)
+        .map_err(|e| http_error(e, timeout))?;
+    let text = response.into_string()?;
+    log::debug(format_args!("raw response:\n{}", text));
+    Ok(text)
+}
+
+/// Map a failed HTTP call to `Error::Api` (non-2xx status), `Error::Timeout`
+/// when it outlived `timeout`, or `Error::Http`.
+pub(crate) fn http_error(err: ureq::Error, timeout: Duration) -> Error {
+    match err {
+        ureq::Error::Status(status, response) => {
+            let body = response.into_string().unwrap_or_default();
+            let message = serde_json::from_str::<ErrorResponse>(&body)
+                .map(|e| e.error.message)
+                .unwrap_or(body);
+            Error::Api { status, message }
+        }
+        ureq::Error::Transport(transport) => {
+            let timed_out = std::error::Error::source(&transport)
+                .and_then(|source| source.downcast_ref::<std::io::Error>())
+                .is_some_and(|io| {
+                    matches!(
+                        io.kind(),
+                        std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+                    )
+                });
+            if timed_out {
+                Error::Timeout(timeout)
+            } else {
+                Error::Http(transport.to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_schema_appends_schema() {
+        let schema = serde_json::json!({"type": "object"});
+        let prompt = describe_schema("Generate a regex", &schema);
+        assert!(prompt.starts_with("Generate a regex\n\n"));
+        assert!(prompt.ends_with(r#"{"type":"object"}"#));
+    }
+
+    #[test]
+    fn usage_accumulates() {
+        let mut metadata = Metadata::default();
+        metadata.add_usage(&Metadata::default());
+        assert!(metadata.usage.is_none());
+
+        let first = Metadata {
+            usage: Some(Usage {
+                input_tokens: 10,
+                output_tokens: 5,
+                cost_usd: Some(0.01),
+                duration_ms: 1000,
+            }),
+            ..Metadata::default()
+        };
+        metadata.add_usage(&first);
+        metadata.add_usage(&first);
+        let usage = metadata.usage.unwrap();
+        assert_eq!(usage.input_tokens, 20);
+        assert_eq!(usage.output_tokens, 10);
+        assert_eq!(usage.cost_usd, Some(0.02));
+        assert_eq!(usage.duration_ms, 2000);
+    }
+
+    #[test]
+    fn usage_cost_stays_unknown_without_reports() {
+        let mut usage = Usage::default();
+        usage += &Usage::default();
+        assert_eq!(usage.cost_usd, None);
+    }
+
+    #[test]
+    fn default_models() {
+        assert_eq!(BackendKind::Claude.default_model(), "haiku");
+        assert_eq!(BackendKind::Anthropic.default_model(), "haiku");
+        assert_eq!(BackendKind::Openai.default_model(), "llama3.2");
+    }
+}
@@ -0,0 +1,222 @@
+use super::{http_agent, send_json, Backend, Reply, Usage, DEFAULT_TIMEOUT};
+use crate::config::{env_var, OpenAiConfig};
+use crate::error::Result;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+/// Ollama's OpenAI-compatible endpoint.
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434/v1";
+pub const DEFAULT_MODEL: &str = "llama3.2";
+
+#[derive(Deserialize, Debug)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: ChatUsage,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct ChatUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Choice {
+    message: Message,
+}
+
+#[derive(Deserialize, Debug)]
+struct Message {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// Speaks the OpenAI chat completions protocol, typically to a local model
+/// server such as Ollama or llama.cpp.
+pub struct OpenAi {
+    api_key: Option<String>,
+    base_url: String,
+    model: String,
+    timeout: Duration,
+}
+
+impl OpenAi {
+    pub fn new(base_url: &str, model: &str) -> Self {
+        OpenAi {
+            api_key: None,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Build a backend from `OPENAI_BASE_URL`/`OPENAI_API_KEY`, falling back
+    /// to the `[openai]` config section. Local servers need no API key.
+    pub fn from_config(config: &OpenAiConfig, model: &str) -> Self {
+        let base_url = env_var("OPENAI_BASE_URL")
+            .or_else(|| config.base_url.clone())
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+
+        let backend = OpenAi::new(&base_url, model);
+        match env_var("OPENAI_API_KEY").or_else(|| config.api_key.clone()) {
+            Some(key) => backend.with_api_key(&key),
+            None => backend,
+        }
+    }
+
+    /// Post `prompt` as a chat completion. With a `schema`, the reply is
+    /// constrained through `response_format`.
+    fn send(&self, prompt: &str, schema: Option<&Value>) -> Result<Reply> {
+        let mut body = json!({
+            "model": self.model,
+            "temperature": 0,
+            "messages": [{"role": "user", "content": prompt}],
+        });
+        if let Some(schema) = schema {
+            body["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": {"name": "response", "schema": schema},
+            });
+        }
+
+        let start = Instant::now();
+        let mut request =
+            http_agent(self.timeout).post(&format!("{}/chat/completions", self.base_url));
+        if let Some(key) = &self.api_key {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+        let text = send_json(request, body, self.timeout)?;
+
+        let response: ChatResponse = serde_json::from_str(&text)?;
+        let usage = Usage {
+            input_tokens: response.usage.prompt_tokens,
+            output_tokens: response.usage.completion_tokens,
+            cost_usd: None,
+            duration_ms: start.elapsed().as_millis() as u64,
+        };
+        let text = response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .unwrap_or_default();
+
+        let mut metadata = self.metadata();
+        metadata.usage = Some(usage);
+        Ok(Reply { text, metadata })
+    }
+}
+
+impl Backend for OpenAi {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn query(&self, prompt: &str) -> Result<Reply> {
+        self.send(prompt, None)
+    }
+
+    fn query_structured(&self, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.send(prompt, Some(schema))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::stub::StubServer;
+    use crate::error::Error;
+
+    #[test]
+    fn new_trims_base_url() {
+        let backend = OpenAi::new("http://localhost:8080/v1/", "qwen2.5");
+        assert_eq!(backend.base_url, "http://localhost:8080/v1");
+        assert_eq!(backend.model(), "qwen2.5");
+    }
+
+    #[test]
+    fn query_sends_chat_completion() {
+        let server = StubServer::respond(
+            200,
+            r#"{"choices": [{"index": 0, "message": {"role": "assistant", "content": "```json\n{\"ok\": true}\n```"}}], "usage": {"prompt_tokens": 30, "completion_tokens": 9, "total_tokens": 39}}"#,
+        );
+        let backend = OpenAi::new(&format!("{}/v1", server.url), "llama3.2");
+        let reply = backend.query("hello").unwrap();
+        assert_eq!(reply.text, "```json\n{\"ok\": true}\n```");
+        assert_eq!(reply.metadata.backend, "openai");
+        assert_eq!(reply.metadata.model, "llama3.2");
+        let usage = reply.metadata.usage.unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (30, 9));
+
+        let request = server.request();
+        assert!(request.starts_with("POST /v1/chat/completions"));
+        assert!(!request.to_lowercase().contains("authorization"));
+        assert!(request.contains(r#""content":"hello""#));
+    }
+
+    #[test]
+    fn structured_query_sets_response_format() {
+        let server = StubServer::respond(200, r#"{"choices": [{"message": {"content": "{}"}}]}"#);
+        let backend = OpenAi::new(&server.url, "llama3.2");
+        let schema = json!({"type": "object"});
+        backend.query_structured("hello", &schema).unwrap();
+        assert!(server.request().contains(
+            r#""response_format":{"json_schema":{"name":"response","schema":{"type":"object"}},"type":"json_schema"}"#
+        ));
+    }
+
+    #[test]
+    fn unresponsive_server_times_out() {
+        // Connections are accepted by the OS but never answered
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let backend = OpenAi::new(&url, "llama3.2").with_timeout(Duration::from_millis(200));
+        assert!(matches!(backend.query("hello"), Err(Error::Timeout(_))));
+    }
+
+    #[test]
+    fn api_key_sent_as_bearer_token() {
+        let server = StubServer::respond(200, r#"{"choices": [{"message": {"content": "{}"}}]}"#);
+        let backend = OpenAi::new(&server.url, "gpt-4o-mini").with_api_key("sk-local");
+        backend.query("hello").unwrap();
+        assert!(server.request().contains("Bearer sk-local"));
+    }
+
+    #[test]
+    fn empty_choices_yield_empty_text() {
+        let server = StubServer::respond(200, r#"{"choices": []}"#);
+        let backend = OpenAi::new(&server.url, "llama3.2");
+        assert_eq!(backend.query("hello").unwrap().text, "");
+        server.request();
+    }
+
+    #[test]
+    fn api_error_surfaced() {
+        let server = StubServer::respond(
+            404,
+            r#"{"error": {"message": "model \"nope\" not found", "type": "api_error"}}"#,
+        );
+        let backend = OpenAi::new(&server.url, "nope");
+        let err = backend.query("hello").unwrap_err();
+        server.request();
+        assert!(matches!(err, Error::Api { status: 404, .. }));
+        assert!(err.to_string().contains("not found"));
+    }
+}
@@ -0,0 +1,58 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+/// Single-shot HTTP server for exercising HTTP backends in tests.
+pub struct StubServer {
+    pub url: String,
+    handle: JoinHandle<String>,
+}
+
+impl StubServer {
+    /// Serve one request with the given status and JSON body.
+    pub fn respond(status: u16, body: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let body = body.to_string();
+
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut head = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                head.push_str(&line);
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+
+            let response = format!(
+                "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+
+            head + &String::from_utf8(request_body).unwrap()
+        });
+
+        StubServer { url, handle }
+    }
+
+    /// Wait for the request to be served and return it verbatim.
+    pub fn request(self) -> String {
+        self.handle.join().unwrap()
+    }
+}
@@ -0,0 +1,325 @@
+use crate::backend::{Backend, Metadata, Reply};
+use crate::config::xdg_dir;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long a cached response is reused by default: one week.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Entry {
+    /// Unix time the response was stored
+    created: u64,
+    text: String,
+    metadata: Metadata,
+}
+
+/// Cache request for a structured query: the prompt and its schema.
+fn structured(prompt: &str, schema: &Value) -> String {
+    format!("{}\0{}", prompt, schema)
+}
+
+/// Hex SHA-256 of the backend identity, model and prompt, used as the
+/// cache key.
+pub fn key(backend: &str, model: &str, prompt: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in [backend, model, prompt] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Backend responses stored as one JSON file per key.
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Cache { dir, ttl }
+    }
+
+    /// `$XDG_CACHE_HOME/rgx`, or `~/.cache/rgx`.
+    pub fn default_dir() -> Option<PathBuf> {
+        xdg_dir("XDG_CACHE_HOME", ".cache")
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// Fresh entry for `key`. Missing, expired and unreadable entries are
+    /// all treated as a miss.
+    fn get(&self, key: &str) -> Option<Reply> {
+        let contents = fs::read_to_string(self.path(key)).ok()?;
+        let entry: Entry = serde_json::from_str(&contents).ok()?;
+        if now().saturating_sub(entry.created) > self.ttl.as_secs() {
+            return None;
+        }
+        Some(Reply {
+            text: entry.text,
+            // A cached answer costs nothing this time round
+            metadata: Metadata {
+                cached: true,
+                usage: None,
+                ..entry.metadata
+            },
+        })
+    }
+
+    fn put(&self, key: &str, reply: &Reply) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = Entry {
+            created: now(),
+            text: reply.text.clone(),
+            metadata: reply.metadata.clone(),
+        };
+        fs::write(self.path(key), serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = fs::remove_file(self.path(key));
+    }
+}
+
+/// Backend wrapper that answers repeated prompts from the on-disk cache.
+pub struct Cached<B> {
+    inner: B,
+    cache: Cache,
+    refresh: bool,
+}
+
+impl<B: Backend> Cached<B> {
+    pub fn new(inner: B, cache: Cache) -> Self {
+        Cached {
+            inner,
+            cache,
+            refresh: false,
+        }
+    }
+
+    /// Skip cache lookups but still store fresh responses.
+    pub fn with_refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+}
+
+impl<B: Backend> Cached<B> {
+    /// Look up `request` in the cache, or answer it with `send` and store
+    /// the reply.
+    fn fetch(&self, request: &str, send: impl FnOnce() -> Result<Reply>) -> Result<Reply> {
+        let key = self.key(request);
+        if !self.refresh {
+            if let Some(reply) = self.cache.get(&key) {
+                return Ok(reply);
+            }
+        }
+
+        let reply = send()?;
+        // A cache that cannot be written should never fail the command
+        let _ = self.cache.put(&key, &reply);
+        Ok(reply)
+    }
+
+    fn key(&self, request: &str) -> String {
+        key(&self.inner.identity(), self.inner.model(), request)
+    }
+}
+
+impl<B: Backend> Backend for Cached<B> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn identity(&self) -> String {
+        self.inner.identity()
+    }
+
+    fn query(&self, prompt: &str) -> Result<Reply> {
+        self.fetch(prompt, || self.inner.query(prompt))
+    }
+
+    fn query_structured(&self, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.fetch(&structured(prompt, schema), || {
+            self.inner.query_structured(prompt, schema)
+        })
+    }
+
+    /// Follow-ups depend on the session's history, so they are never cached.
+    fn resume(&self, session_id: &str, prompt: &str) -> Result<Reply> {
+        self.inner.resume(session_id, prompt)
+    }
+
+    fn resume_structured(&self, session_id: &str, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.inner.resume_structured(session_id, prompt, schema)
+    }
+
+    /// Replies are stored before the command parses them, so one that does
+    /// not parse is removed again.
+    fn discard(&self, prompt: &str, schema: &Value) {
+        self.cache.remove(&self.key(&structured(prompt, schema)));
+        self.inner.discard(prompt, schema)
+    }
+
+    fn metadata(&self) -> Metadata {
+        self.inner.metadata()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::MockBackend;
+
+    fn cached(dir: &tempfile::TempDir, responses: &[&str]) -> Cached<MockBackend> {
+        Cached::new(
+            MockBackend::new(responses.iter().copied()),
+            Cache::new(dir.path().to_path_buf(), DEFAULT_TTL),
+        )
+    }
+
+    #[test]
+    fn key_depends_on_every_part() {
+        let base = key("claude", "haiku", "email");
+        assert_eq!(base.len(), 64);
+        assert_eq!(base, key("claude", "haiku", "email"));
+        assert_ne!(base, key("anthropic", "haiku", "email"));
+        assert_ne!(base, key("claude", "sonnet", "email"));
+        assert_ne!(base, key("claude", "haiku", "emails"));
+        // Parts are separated, so shifting text between them changes the key
+        assert_ne!(key("ab", "c", "d"), key("a", "bc", "d"));
+    }
+
+    #[test]
+    fn second_query_served_from_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = cached(&dir, &["first"]);
+
+        let reply = backend.query("prompt").unwrap();
+        assert_eq!(reply.text, "first");
+        assert!(!reply.metadata.cached);
+
+        let reply = backend.query("prompt").unwrap();
+        assert_eq!(reply.text, "first");
+        assert!(reply.metadata.cached);
+        assert_eq!(backend.inner.prompts().len(), 1);
+    }
+
+    #[test]
+    fn schema_is_part_of_the_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = cached(&dir, &["plain", "one", "two"]);
+        let a = serde_json::json!({"type": "object"});
+        let b = serde_json::json!({"type": "object", "required": ["x"]});
+        assert_eq!(backend.query("p").unwrap().text, "plain");
+        assert_eq!(backend.query_structured("p", &a).unwrap().text, "one");
+        assert_eq!(backend.query_structured("p", &b).unwrap().text, "two");
+        assert!(backend.query_structured("p", &a).unwrap().metadata.cached);
+    }
+
+    #[test]
+    fn discarded_replies_are_not_served_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = serde_json::json!({"type": "object"});
+        let backend = cached(&dir, &["not json", "{}"]);
+        backend.query_structured("p", &schema).unwrap();
+        backend.discard("p", &schema);
+
+        let reply = backend.query_structured("p", &schema).unwrap();
+        assert_eq!(reply.text, "{}");
+        assert!(!reply.metadata.cached);
+        assert!(
+            backend
+                .query_structured("p", &schema)
+                .unwrap()
+                .metadata
+                .cached
+        );
+    }
+
+    #[test]
+    fn different_prompts_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = cached(&dir, &["one", "two"]);
+        assert_eq!(backend.query("a").unwrap().text, "one");
+        assert_eq!(backend.query("b").unwrap().text, "two");
+    }
+
+    #[test]
+    fn refresh_bypasses_lookup_but_stores() {
+        let dir = tempfile::tempdir().unwrap();
+        cached(&dir, &["old"]).query("prompt").unwrap();
+
+        let refreshed = cached(&dir, &["new"]).with_refresh(true);
+        assert_eq!(refreshed.query("prompt").unwrap().text, "new");
+
+        let reply = cached(&dir, &[]).query("prompt").unwrap();
+        assert_eq!(reply.text, "new");
+        assert!(reply.metadata.cached);
+    }
+
+    #[test]
+    fn expired_entries_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(dir.path().to_path_buf(), Duration::from_secs(60));
+        let stale = Entry {
+            created: now() - 120,
+            text: "stale".to_string(),
+            metadata: Metadata::default(),
+        };
+        fs::write(cache.path("k"), serde_json::to_string(&stale).unwrap()).unwrap();
+        assert!(cache.get("k").is_none());
+    }
+
+    #[test]
+    fn corrupt_entries_are_a_miss() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(dir.path().to_path_buf(), DEFAULT_TTL);
+        fs::write(cache.path("k"), "{not json").unwrap();
+        assert!(cache.get("k").is_none());
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = cached(&dir, &[]);
+        assert!(backend.query("prompt").is_err());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn unwritable_cache_does_not_fail_query() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("not-a-dir");
+        fs::write(&file, "").unwrap();
+        let backend = Cached::new(
+            MockBackend::new(["ok"]),
+            Cache::new(file.join("rgx"), DEFAULT_TTL),
+        );
+        assert_eq!(backend.query("prompt").unwrap().text, "ok");
+    }
+}
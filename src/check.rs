@@ -0,0 +1,580 @@
+//! Static flavor-compatibility check: find the constructs in a pattern that
+//! its target flavor does not support, without compiling it.
+//!
+//! The scanner reads the union of the flavors' syntax, so a construct is
+//! recognized wherever it appears and then judged against the flavor's
+//! `Capabilities`. The exceptions are a backslash inside brackets, which
+//! POSIX reads as an ordinary member rather than an escape, and basic
+//! syntax, where `(`, `{`, `+` and `?` are ordinary characters that open no
+//! group and form no quantifier suffix.
+
+use crate::flavor::{Capabilities, Flavor};
+use serde::Serialize;
+use std::ops::Range;
+
+/// A piece of syntax that not every flavor supports.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Construct {
+    Lookahead,
+    Lookbehind,
+    Backreference,
+    NamedGroup,
+    NonCapturingGroup,
+    AtomicGroup,
+    InlineFlags,
+    LazyQuantifier,
+    PossessiveQuantifier,
+    ShorthandClass,
+    UnicodeClass,
+    PosixClass,
+    WordBoundary,
+    StringAnchor,
+    Recursion,
+    LetterEscape,
+    Alternation,
+}
+
+impl Construct {
+    /// Name used in messages.
+    pub fn describe(self) -> &'static str {
+        match self {
+            Construct::Lookahead => "lookahead",
+            Construct::Lookbehind => "lookbehind",
+            Construct::Backreference => "backreference",
+            Construct::NamedGroup => "named group",
+            Construct::NonCapturingGroup => "non-capturing group",
+            Construct::AtomicGroup => "atomic group",
+            Construct::InlineFlags => "inline flags",
+            Construct::LazyQuantifier => "lazy quantifier",
+            Construct::PossessiveQuantifier => "possessive quantifier",
+            Construct::ShorthandClass => "shorthand class",
+            Construct::UnicodeClass => "Unicode property class",
+            Construct::PosixClass => "POSIX class",
+            Construct::WordBoundary => "word boundary",
+            Construct::StringAnchor => "string anchor",
+            Construct::Recursion => "recursion",
+            Construct::LetterEscape => "escape",
+            Construct::Alternation => "alternation",
+        }
+    }
+
+    /// Whether a flavor with `caps` supports this construct written as `text`.
+    pub fn supported(self, text: &str, caps: &Capabilities) -> bool {
+        match self {
+            Construct::Lookahead => caps.lookahead,
+            Construct::Lookbehind => caps.lookbehind,
+            // Named references are spelled like the named groups they refer to
+            Construct::Backreference if text.starts_with("(?P=") => {
+                caps.backreferences && caps.named_groups.contains(&"(?P<name>...)")
+            }
+            Construct::Backreference if text.starts_with("\\k") => {
+                caps.backreferences && caps.named_groups.contains(&"(?<name>...)")
+            }
+            Construct::Backreference => caps.backreferences,
+            Construct::NamedGroup => caps
+                .named_groups
+                .iter()
+                .any(|form| text.starts_with(&form[..form.find("name").unwrap_or(0)])),
+            Construct::NonCapturingGroup => caps.non_capturing_groups,
+            Construct::AtomicGroup => caps.atomic_groups,
+            Construct::InlineFlags => caps.inline_flags,
+            Construct::LazyQuantifier => caps.lazy_quantifiers,
+            Construct::PossessiveQuantifier => caps.possessive_quantifiers,
+            Construct::ShorthandClass => caps.shorthand_classes,
+            Construct::UnicodeClass => caps.unicode_classes,
+            Construct::PosixClass => caps.posix_classes,
+            Construct::WordBoundary => caps.word_boundaries,
+            Construct::StringAnchor => match text {
+                r"\A" => caps.string_anchors,
+                r"\Z" => caps.newline_end_anchor || caps.end_anchor == Some(text),
+                _ => caps.end_anchor == Some(text),
+            },
+            Construct::Recursion => caps.recursion,
+            Construct::LetterEscape => caps.letter_escapes,
+            Construct::Alternation => !caps.basic_syntax,
+        }
+    }
+}
+
+/// One flavor-dependent construct found in a pattern, with its byte span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub construct: Construct,
+    pub span: Range<usize>,
+    /// Whether it sits inside a bracket expression
+    pub in_class: bool,
+}
+
+/// A construct the target flavor does not support.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub construct: Construct,
+    /// Byte offset of the construct in the pattern
+    pub start: usize,
+    pub end: usize,
+    /// The construct as written
+    pub text: String,
+    pub message: String,
+}
+
+/// Every flavor-dependent construct in `pattern`, written for a flavor
+/// with `caps`, in order.
+pub fn scan(pattern: &str, caps: &Capabilities) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    // Byte offset of each char index, plus one past the end
+    let offsets: Vec<usize> = pattern
+        .char_indices()
+        .map(|(at, _)| at)
+        .chain([pattern.len()])
+        .collect();
+    let mut tokens = Vec::new();
+    let mut push = |construct, from: usize, to: usize, in_class| {
+        tokens.push(Token {
+            construct,
+            span: offsets[from]..offsets[to.min(chars.len())],
+            in_class,
+        })
+    };
+    let mut in_class = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && (caps.bracket_escapes || !in_class) {
+            let (construct, len) = escape(&chars[i..], in_class);
+            if let Some(construct) = construct {
+                push(construct, i, i + len, in_class);
+            }
+            i += len;
+            continue;
+        }
+
+        if in_class {
+            if c == ']' {
+                in_class = false;
+            } else if c == '[' && chars.get(i + 1) == Some(&':') {
+                if let Some(end) = find(&chars, i + 2, ":]") {
+                    push(Construct::PosixClass, i, end + 2, true);
+                    i = end + 2;
+                    continue;
+                }
+            }
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '[' => {
+                in_class = true;
+                i += 1;
+                // A leading ] (after an optional ^) is a literal member
+                if chars.get(i) == Some(&'^') {
+                    i += 1;
+                }
+                if chars.get(i) == Some(&']') {
+                    i += 1;
+                }
+                continue;
+            }
+            '(' if chars.get(i + 1) == Some(&'?') && !caps.basic_syntax => {
+                if let Some((construct, len)) = group(&chars[i..]) {
+                    push(construct, i, i + len, false);
+                    i += len;
+                    continue;
+                }
+            }
+            '*' | '+' | '?' | '{' if !caps.basic_syntax => {
+                let len = if c == '{' {
+                    interval_len(&chars[i..])
+                } else {
+                    1
+                };
+                if len > 0 {
+                    match chars.get(i + len) {
+                        Some('?') => push(Construct::LazyQuantifier, i, i + len + 1, false),
+                        Some('+') => push(Construct::PossessiveQuantifier, i, i + len + 1, false),
+                        _ => {}
+                    }
+                    let suffix = matches!(chars.get(i + len), Some('?' | '+'));
+                    i += len + usize::from(suffix);
+                    continue;
+                }
+            }
+            '|' => push(Construct::Alternation, i, i + 1, false),
+            _ => {}
+        }
+        i += 1;
+    }
+    tokens
+}
+
+/// The construct an escape at the start of `chars` writes, if any, and its
+/// length in chars.
+fn escape(chars: &[char], in_class: bool) -> (Option<Construct>, usize) {
+    let Some(&next) = chars.get(1) else {
+        return (None, 1);
+    };
+    match next {
+        'd' | 'D' | 'w' | 'W' | 's' | 'S' => (Some(Construct::ShorthandClass), 2),
+        'p' | 'P' => {
+            let len = match chars.get(2) {
+                Some('{') => find(chars, 3, "}").map_or(chars.len(), |end| end + 1),
+                Some(_) => 3,
+                None => 2,
+            };
+            (Some(Construct::UnicodeClass), len)
+        }
+        'b' | 'B' if !in_class => (Some(Construct::WordBoundary), 2),
+        'A' | 'z' | 'Z' if !in_class => (Some(Construct::StringAnchor), 2),
+        '1'..='9' if !in_class => {
+            let digits = chars[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+            (Some(Construct::Backreference), 1 + digits)
+        }
+        'k' if !in_class => {
+            let close = match chars.get(2) {
+                Some('<') => ">",
+                Some('\'') => "'",
+                Some('{') => "}",
+                _ => return (Some(Construct::LetterEscape), 2),
+            };
+            let len = find(chars, 3, close).map_or(2, |end| end + 1);
+            (Some(Construct::Backreference), len)
+        }
+        c if c.is_ascii_alphanumeric() => (Some(Construct::LetterEscape), 2),
+        _ => (None, 2),
+    }
+}
+
+/// The construct a `(?` group at the start of `chars` opens, and the length
+/// of its opening syntax.
+fn group(chars: &[char]) -> Option<(Construct, usize)> {
+    let rest: String = chars.iter().take(5).collect();
+    let through = |from: usize, close: &str| find(chars, from, close).map(|end| end + close.len());
+
+    if rest.starts_with("(?<=") || rest.starts_with("(?<!") {
+        Some((Construct::Lookbehind, 4))
+    } else if rest.starts_with("(?=") || rest.starts_with("(?!") {
+        Some((Construct::Lookahead, 3))
+    } else if rest.starts_with("(?:") {
+        Some((Construct::NonCapturingGroup, 3))
+    } else if rest.starts_with("(?>") {
+        Some((Construct::AtomicGroup, 3))
+    } else if rest.starts_with("(?P<") {
+        Some((Construct::NamedGroup, through(4, ">")?))
+    } else if rest.starts_with("(?<") || rest.starts_with("(?'") {
+        let close = if chars[2] == '<' { ">" } else { "'" };
+        Some((Construct::NamedGroup, through(3, close)?))
+    } else if rest.starts_with("(?P=") {
+        Some((Construct::Backreference, through(4, ")")?))
+    } else if rest.starts_with("(?P>") || rest.starts_with("(?&") || numbered_call(chars) {
+        Some((Construct::Recursion, through(3, ")")?))
+    } else {
+        let flags = chars[2..]
+            .iter()
+            .take_while(|c| c.is_ascii_alphabetic() || **c == '-')
+            .count();
+        match chars.get(2 + flags) {
+            Some(')' | ':') if flags > 0 => Some((Construct::InlineFlags, 3 + flags)),
+            _ => None,
+        }
+    }
+}
+
+/// `(?R)`, `(?1)`, `(?+1)` or `(?-1)` at the start of `chars`.
+fn numbered_call(chars: &[char]) -> bool {
+    let body: String = chars[2..].iter().take_while(|c| **c != ')').collect();
+    let closed = chars.len() > 2 + body.chars().count();
+    let digits = body.strip_prefix(['+', '-']).unwrap_or(&body);
+    closed && (body == "R" || !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Length of a valid `{n}`, `{n,}` or `{n,m}` interval at the start of
+/// `chars`, or 0 when the brace is a literal.
+fn interval_len(chars: &[char]) -> usize {
+    let Some(end) = find(chars, 1, "}") else {
+        return 0;
+    };
+    let inner: String = chars[1..end].iter().collect();
+    let mut bounds = inner.splitn(2, ',');
+    let valid = bounds
+        .next()
+        .is_some_and(|min| !min.is_empty() && min.chars().all(|c| c.is_ascii_digit()))
+        && bounds
+            .next()
+            .is_none_or(|max| max.chars().all(|c| c.is_ascii_digit()));
+    if valid {
+        end + 1
+    } else {
+        0
+    }
+}
+
+/// Char index of the first `needle` at or after `from`.
+fn find(chars: &[char], from: usize, needle: &str) -> Option<usize> {
+    let needle: Vec<char> = needle.chars().collect();
+    (from..chars.len()).find(|&i| chars[i..].starts_with(&needle))
+}
+
+/// Every construct in `pattern` that `flavor` does not support.
+pub fn check(pattern: &str, flavor: Flavor) -> Vec<Issue> {
+    let caps = flavor.capabilities();
+    scan(pattern, caps)
+        .into_iter()
+        .filter_map(|token| {
+            let text = &pattern[token.span.clone()];
+            if token.construct.supported(text, caps) {
+                return None;
+            }
+            Some(issue(token.construct, text, token.span.start, flavor))
+        })
+        .collect()
+}
+
+/// `construct`, written as `text` at byte offset `start`, reported as
+/// unsupported by `flavor`.
+pub fn issue(construct: Construct, text: &str, start: usize, flavor: Flavor) -> Issue {
+    Issue {
+        construct,
+        start,
+        end: start + text.len(),
+        text: text.to_string(),
+        message: message(construct, text, flavor),
+    }
+}
+
+fn message(construct: Construct, text: &str, flavor: Flavor) -> String {
+    let unsupported = format!(
+        "{} {} is not supported by {}",
+        construct.describe(),
+        text,
+        flavor.label()
+    );
+    let caps = flavor.capabilities();
+    let hint = match construct {
+        Construct::NamedGroup => match caps.named_groups {
+            [] => "use a plain numbered group".to_string(),
+            [preferred, ..] => format!("write it as {}", preferred),
+        },
+        Construct::ShorthandClass if caps.posix_classes => match &text[1..] {
+            "d" => "use [[:digit:]]".to_string(),
+            "D" => "use [^[:digit:]]".to_string(),
+            "w" => "use [[:alnum:]_]".to_string(),
+            "W" => "use [^[:alnum:]_]".to_string(),
+            "s" => "use [[:space:]]".to_string(),
+            _ => "use [^[:space:]]".to_string(),
+        },
+        Construct::PosixClass if caps.shorthand_classes => {
+            "use an explicit range or a shorthand like \\d".to_string()
+        }
+        Construct::StringAnchor => match caps.end_anchor {
+            Some(end) if text != r"\A" => format!("write the very end of the input as {}", end),
+            _ => "use ^ and $ without multi-line mode".to_string(),
+        },
+        Construct::Alternation => {
+            "there is no alternation; write [|] for a literal | or one pattern per alternative"
+                .to_string()
+        }
+        _ => return unsupported,
+    };
+    format!("{}; {}", unsupported, hint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+    use clap::ValueEnum;
+
+    fn constructs(pattern: &str, flavor: Flavor) -> Vec<(Construct, &str)> {
+        check(pattern, flavor)
+            .iter()
+            .map(|issue| (issue.construct, &pattern[issue.start..issue.end]))
+            .collect()
+    }
+
+    #[test]
+    fn scan_finds_spans() {
+        let tokens = scan(r"(?<year>\d{4})(?=-)\1", Flavor::Pcre.capabilities());
+        assert_eq!(
+            tokens,
+            vec![
+                Token {
+                    construct: Construct::NamedGroup,
+                    span: 0..8,
+                    in_class: false
+                },
+                Token {
+                    construct: Construct::ShorthandClass,
+                    span: 8..10,
+                    in_class: false
+                },
+                Token {
+                    construct: Construct::Lookahead,
+                    span: 14..17,
+                    in_class: false
+                },
+                Token {
+                    construct: Construct::Backreference,
+                    span: 19..21,
+                    in_class: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_are_byte_offsets() {
+        let issues = check(r"é\d", Flavor::Posix);
+        assert_eq!((issues[0].start, issues[0].end), (2, 4));
+    }
+
+    #[test]
+    fn posix_flags_shorthand_with_replacement() {
+        let issues = check(r"^\d+\s\W$", Flavor::Posix);
+        assert_eq!(issues.len(), 3);
+        assert!(issues[0].message.contains("use [[:digit:]]"));
+        assert!(issues[2].message.contains("use [^[:alnum:]_]"));
+    }
+
+    #[test]
+    fn rust_flags_lookaround_and_backreferences() {
+        assert_eq!(
+            constructs(r"(?<=\$)(\d+)(?!px)\1", Flavor::Rust),
+            vec![
+                (Construct::Lookbehind, "(?<="),
+                (Construct::Lookahead, "(?!"),
+                (Construct::Backreference, r"\1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn named_group_forms_depend_on_flavor() {
+        assert!(check("(?P<id>x)", Flavor::Rust).is_empty());
+        let issues = check("(?P<id>x)", Flavor::Js);
+        assert_eq!(issues[0].text, "(?P<id>");
+        assert!(issues[0].message.contains("write it as (?<name>...)"));
+        assert!(check("(?'id'x)", Flavor::Pcre).is_empty());
+        assert!(check("(?<id>x)", Flavor::Posix)[0]
+            .message
+            .contains("plain numbered group"));
+    }
+
+    #[test]
+    fn named_references_follow_group_syntax() {
+        assert!(check(r"(?<a>x)\k<a>", Flavor::Js).is_empty());
+        assert_eq!(
+            constructs(r"(?<a>x)(?P=a)", Flavor::Js),
+            vec![(Construct::Backreference, "(?P=a)")]
+        );
+        assert!(scan(r"[\d]", Flavor::Pcre.capabilities())[0].in_class);
+        assert!(scan(r"[\d]", Flavor::Posix.capabilities()).is_empty());
+    }
+
+    #[test]
+    fn js_flags_string_anchors() {
+        assert_eq!(
+            constructs(r"\Aabc\z", Flavor::Js),
+            vec![
+                (Construct::StringAnchor, r"\A"),
+                (Construct::StringAnchor, r"\z")
+            ]
+        );
+        assert!(check(r"\Aabc\z", Flavor::Rust).is_empty());
+    }
+
+    #[test]
+    fn end_anchors_depend_on_the_flavor() {
+        assert!(check(r"\Aa\Z", Flavor::Pcre).is_empty());
+        assert!(check(r"\Aa\Z", Flavor::Python).is_empty());
+        let issues = check(r"a\Z", Flavor::Rust);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0]
+            .message
+            .ends_with("write the very end of the input as \\z"));
+        assert!(check(r"a\z", Flavor::Python)[0]
+            .message
+            .ends_with("write the very end of the input as \\Z"));
+        assert!(check(r"a\Z", Flavor::Js)[0].message.contains("use ^ and $"));
+    }
+
+    #[test]
+    fn quantifier_suffixes() {
+        assert_eq!(
+            constructs("a+?b{2,}+c*", Flavor::Rust),
+            vec![(Construct::PossessiveQuantifier, "{2,}+")]
+        );
+        assert_eq!(
+            constructs("a+?b{2,}+", Flavor::Posix),
+            vec![
+                (Construct::LazyQuantifier, "+?"),
+                (Construct::PossessiveQuantifier, "{2,}+"),
+            ]
+        );
+        assert!(check("a{x}?", Flavor::Posix).is_empty());
+    }
+
+    #[test]
+    fn class_contents() {
+        assert_eq!(
+            constructs(r"[[:alpha:]\d\b(?:]", Flavor::Js),
+            vec![(Construct::PosixClass, "[:alpha:]")]
+        );
+        // A backslash inside POSIX brackets is an ordinary member
+        assert!(check(r"[[:alpha:]\d]", Flavor::Posix).is_empty());
+        assert_eq!(
+            constructs(r"[[:alpha:]]\d", Flavor::Posix),
+            vec![(Construct::ShorthandClass, r"\d")]
+        );
+        assert!(check(r"[]?+]", Flavor::Posix).is_empty());
+    }
+
+    #[test]
+    fn groups_flags_and_recursion() {
+        assert_eq!(
+            constructs(r"(?i)(?>a)(?R)(?1)(?&x)\p{L}", Flavor::Rust),
+            vec![
+                (Construct::AtomicGroup, "(?>"),
+                (Construct::Recursion, "(?R)"),
+                (Construct::Recursion, "(?1)"),
+                (Construct::Recursion, "(?&x)"),
+            ]
+        );
+        assert_eq!(
+            constructs(r"(?i:a)\x41\.", Flavor::Posix),
+            vec![
+                (Construct::InlineFlags, "(?i:"),
+                (Construct::LetterEscape, r"\x")
+            ]
+        );
+    }
+
+    #[test]
+    fn plain_patterns_pass_everywhere() {
+        for flavor in Flavor::value_variants() {
+            if !flavor.capabilities().basic_syntax {
+                assert!(check(r"^(ab|c)+[a-z]{2,3}\.x?$", *flavor).is_empty());
+            }
+        }
+        assert!(check(r"^\(ab\)*[a-z]\{2,3\}\.\1$", Flavor::Bre).is_empty());
+    }
+
+    #[test]
+    fn basic_syntax_has_no_alternation() {
+        assert_eq!(
+            check("cat|dog", Flavor::Bre)[0].message,
+            "alternation | is not supported by POSIX BRE; there is no alternation; write [|] for a literal | or one pattern per alternative"
+        );
+        assert!(constructs("[|]", Flavor::Bre).is_empty());
+    }
+
+    #[test]
+    fn basic_syntax_has_no_suffixes_or_groups() {
+        for pattern in ["[0-9]*+[0-9]*", "a+?", "(?:x)", r"a\{2\}+"] {
+            assert!(check(pattern, Flavor::Bre).is_empty(), "{}", pattern);
+            assert!(Engine::compile(pattern, Flavor::Bre).is_ok(), "{}", pattern);
+        }
+        assert!(Engine::compile("a+?", Flavor::Bre).unwrap().is_match("a+?"));
+    }
+}
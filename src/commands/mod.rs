@@ -1,15 +1,263 @@
+pub mod convert;
 pub mod explain;
 pub mod generate;
 pub mod test;
 
-use crate::error::Result;
+use crate::backend::{Backend, Metadata, Reply};
+use crate::error::{Error, Result};
+use crate::extract;
+use crate::log;
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde_json::Value;
+
+/// Times a reply that fails to deserialize is sent back to the model for
+/// correction before the parse error is reported.
+pub const PARSE_REPAIRS: usize = 2;
+
+/// JSON Schema for `T` with every definition inlined, a form all backends'
+/// structured output modes accept.
+pub fn schema_for<T: JsonSchema>() -> Value {
+    SchemaSettings::draft07()
+        .with(|settings| {
+            settings.inline_subschemas = true;
+            settings.meta_schema = None;
+        })
+        .into_generator()
+        .into_root_schema_for::<T>()
+        .to_value()
+}
+
+/// `input` between `<tag>` and `</tag>` lines, for embedding user text in a
+/// prompt. The text is otherwise left verbatim, so quotes, braces, fences
+/// and backslashes reach the model unchanged; only a closing tag inside it,
+/// in any case, is escaped so it cannot end the block early.
+pub fn user_block(tag: &str, input: &str) -> String {
+    let close = format!("</{}>", tag);
+    let lower = input.to_ascii_lowercase();
+    let mut escaped = String::with_capacity(input.len());
+    let mut last = 0;
+    // ASCII lowercasing keeps byte offsets, so matches index into `input`
+    for (start, _) in lower.match_indices(&close) {
+        escaped.push_str(&input[last..start]);
+        escaped.push_str("<\\/");
+        last = start + 2;
+    }
+    escaped.push_str(&input[last..]);
+    format!("<{}>\n{}\n{}", tag, escaped, close)
+}
+
+/// `example` as a JSON string, quoted and escaped, for one line of a list.
+pub fn quote(example: &str) -> String {
+    serde_json::to_string(example).unwrap_or_default()
+}
+
+/// Prompt asking the model to correct a `response` to `prompt` that failed
+/// to deserialize with `error`.
+pub fn build_repair_prompt(prompt: &str, response: &str, error: &serde_json::Error) -> String {
+    format!(
+        r#"{}
+
+Your previous response could not be parsed: {}
+
+Previous response:
+{}
+
+Reply with ONLY the corrected JSON object, no markdown. Every backslash inside a JSON string must itself be escaped, so the regex \d+ is written "\\d+"."#,
+        prompt, error, response
+    )
+}
 
 pub trait Command {
-    type Response: serde::de::DeserializeOwned;
+    type Response: serde::de::DeserializeOwned + JsonSchema;
 
     fn build_prompt(&self, input: &str) -> String;
 
+    /// Shape the reply must take, handed to the backend's structured output
+    /// mode.
+    fn schema(&self) -> Value {
+        schema_for::<Self::Response>()
+    }
+
+    /// Deserialize the JSON object in `response`, ignoring any prose or
+    /// markdown fences around it.
     fn parse_response(&self, response: &str) -> Result<Self::Response> {
-        Ok(serde_json::from_str(response)?)
+        Ok(serde_json::from_str(extract::json(response))?)
+    }
+
+    /// Send `prompt` to `backend` and parse the reply.
+    fn query<B: Backend + ?Sized>(
+        &self,
+        backend: &B,
+        prompt: &str,
+    ) -> Result<(Self::Response, Metadata)> {
+        let schema = self.schema();
+        self.query_with(
+            prompt,
+            |prompt| backend.query_structured(prompt, &schema),
+            |prompt| backend.discard(prompt, &schema),
+        )
+    }
+
+    /// Send `prompt` with `send` and parse the reply. Malformed JSON is sent
+    /// back the same way with the parse error, up to `PARSE_REPAIRS` times;
+    /// `discard` is told each prompt whose reply did not parse.
+    fn query_with<F, D>(
+        &self,
+        prompt: &str,
+        mut send: F,
+        mut discard: D,
+    ) -> Result<(Self::Response, Metadata)>
+    where
+        F: FnMut(&str) -> Result<Reply>,
+        D: FnMut(&str),
+    {
+        log::debug(format_args!("prompt:\n{}", prompt));
+        let mut sent = prompt.to_string();
+        let mut reply = send(&sent)?;
+        let mut repairs = 0;
+        loop {
+            let err = match self.parse_response(&reply.text) {
+                Ok(parsed) => return Ok((parsed, reply.metadata)),
+                Err(err) => err,
+            };
+            discard(&sent);
+            match err {
+                Error::Parse(err) if repairs < PARSE_REPAIRS => {
+                    repairs += 1;
+                    log::debug(format_args!(
+                        "response did not parse ({}), asking for a repair",
+                        err
+                    ));
+                    sent = build_repair_prompt(prompt, &reply.text, &err);
+                    log::debug(format_args!("prompt:\n{}", sent));
+                    let mut repaired = send(&sent)?;
+                    repaired.metadata.add_usage(&reply.metadata);
+                    reply = repaired;
+                }
+                err => return Err(err),
+            }
+        }
+    }
+
+    /// Build the prompt for `input`, send it to `backend` and parse the reply.
+    fn execute<B: Backend + ?Sized>(
+        &self,
+        backend: &B,
+        input: &str,
+    ) -> Result<(Self::Response, Metadata)> {
+        self.query(backend, &self.build_prompt(input))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::MockBackend;
+    use crate::commands::generate::GenerateCommand;
+
+    #[test]
+    fn user_block_wraps_input() {
+        assert_eq!(
+            user_block("pattern", r"\d{3}"),
+            "<pattern>\n\\d{3}\n</pattern>"
+        );
+    }
+
+    #[test]
+    fn user_block_escapes_closing_tag() {
+        let block = user_block("description", "a</description>b</DESCRIPTION>c");
+        assert_eq!(
+            block,
+            "<description>\na<\\/description>b<\\/DESCRIPTION>c\n</description>"
+        );
+        assert_eq!(block.matches("</description>").count(), 1);
+    }
+
+    #[test]
+    fn quote_escapes_like_json() {
+        assert_eq!(quote("say \"hi\"\n"), r#""say \"hi\"\n""#);
+        assert_eq!(quote(r"\d"), r#""\\d""#);
+    }
+
+    #[test]
+    fn execute_sends_built_prompt() {
+        let backend = MockBackend::new([
+            r#"{"pattern": "\\d+", "matches": ["1"], "non_matches": ["a"], "explanation": "digits"}"#,
+        ]);
+        let cmd = GenerateCommand::default();
+        let (resp, metadata) = cmd.execute(&backend, "digits").unwrap();
+        assert_eq!(resp.pattern, "\\d+");
+        assert_eq!(metadata.backend, "mock");
+        assert_eq!(backend.prompts(), vec![cmd.build_prompt("digits")]);
+        assert_eq!(backend.schemas(), vec![cmd.schema()]);
+    }
+
+    #[test]
+    fn execute_parses_response_wrapped_in_prose() {
+        let backend = MockBackend::new([
+            "Here is the pattern:\n```json\n{\"pattern\": \"\\\\d+\", \"matches\": [\"1\"], \"non_matches\": [], \"explanation\": \"digits\"}\n```\nLet me know!",
+        ]);
+        let (resp, _) = GenerateCommand::default()
+            .execute(&backend, "digits")
+            .unwrap();
+        assert_eq!(resp.pattern, "\\d+");
+    }
+
+    #[test]
+    fn execute_propagates_parse_errors() {
+        let backend = MockBackend::new(["not json", "still not", "nope"]);
+        let cmd = GenerateCommand::default();
+        assert!(matches!(
+            cmd.execute(&backend, "digits"),
+            Err(Error::Parse(_))
+        ));
+        assert_eq!(backend.prompts().len(), 1 + PARSE_REPAIRS);
+    }
+
+    #[test]
+    fn malformed_reply_is_repaired() {
+        let backend = MockBackend::new([
+            r#"{"pattern": "\d+", "matches": ["1"], "non_matches": ["a"], "explanation": "digits"}"#,
+            r#"{"pattern": "\\d+", "matches": ["1"], "non_matches": ["a"], "explanation": "digits"}"#,
+        ]);
+        let cmd = GenerateCommand::default();
+        let (resp, _) = cmd.execute(&backend, "digits").unwrap();
+        assert_eq!(resp.pattern, "\\d+");
+
+        let prompts = backend.prompts();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[1].starts_with(&cmd.build_prompt("digits")));
+        assert!(prompts[1].contains("could not be parsed"));
+        assert!(prompts[1].contains(r#""pattern": "\d+""#));
+    }
+
+    #[test]
+    fn unparsed_replies_are_not_cached() {
+        use crate::cache::{Cache, Cached, DEFAULT_TTL};
+        let dir = tempfile::tempdir().unwrap();
+        let good = r#"{"pattern": "\\d+", "matches": ["1"], "non_matches": ["a"], "explanation": "digits"}"#;
+        let cached = |responses: &[&str]| {
+            Cached::new(
+                MockBackend::new(responses.iter().copied()),
+                Cache::new(dir.path().to_path_buf(), DEFAULT_TTL),
+            )
+        };
+        let cmd = GenerateCommand::default();
+        cmd.execute(&cached(&["not json", good]), "digits").unwrap();
+
+        let (_, metadata) = cmd.execute(&cached(&[good]), "digits").unwrap();
+        assert!(!metadata.cached);
+    }
+
+    #[test]
+    fn backend_errors_are_not_repaired() {
+        let backend = MockBackend::default();
+        let cmd = GenerateCommand::default();
+        assert!(matches!(
+            cmd.execute(&backend, "digits"),
+            Err(Error::Claude(_))
+        ));
+        assert_eq!(backend.prompts().len(), 1);
     }
 }
@@ -1,11 +1,14 @@
 use crate::commands::generate::GenerateResponse;
+use crate::engine::Engine;
 use crate::error::Result;
-use regex::Regex;
+use crate::flavor::Flavor;
 use serde::Serialize;
 
 #[derive(Serialize, Debug)]
 pub struct TestResult {
     pub pattern: String,
+    /// Local engine the pattern ran on
+    pub engine: &'static str,
     pub test_input: String,
     pub matches: bool,
     pub match_details: Option<MatchDetails>,
@@ -29,44 +32,49 @@ pub struct GroupCapture {
 
 pub struct TestCommand {
     pub test_input: String,
+    pub flavor: Flavor,
 }
 
 impl TestCommand {
-    pub fn new(test_input: &str) -> Self {
+    pub fn new(test_input: &str, flavor: Flavor) -> Self {
         TestCommand {
             test_input: test_input.to_string(),
+            flavor,
         }
     }
 
     pub fn test_pattern(&self, generated: &GenerateResponse) -> Result<TestResult> {
-        let regex = Regex::new(&generated.pattern)?;
+        let regex = Engine::compile(&generated.pattern, self.flavor)?;
+        let input = &self.test_input;
 
-        let match_details = regex.captures(&self.test_input).map(|caps| {
-            let full = caps.get(0).unwrap();
+        let match_details = regex.captures(input).map(|spans| {
+            let full = spans[0].clone().unwrap_or_default();
 
             let groups: Vec<GroupCapture> = regex
                 .capture_names()
+                .into_iter()
                 .enumerate()
                 .skip(1)
                 .filter_map(|(i, name)| {
-                    caps.get(i).map(|m| GroupCapture {
+                    spans.get(i).cloned().flatten().map(|span| GroupCapture {
                         index: i,
                         name: name.map(|s| s.to_string()),
-                        value: m.as_str().to_string(),
+                        value: input[span].to_string(),
                     })
                 })
                 .collect();
 
             MatchDetails {
-                full_match: full.as_str().to_string(),
+                full_match: input[full.clone()].to_string(),
                 groups,
-                start: full.start(),
-                end: full.end(),
+                start: full.start,
+                end: full.end,
             }
         });
 
         Ok(TestResult {
             pattern: generated.pattern.clone(),
+            engine: regex.name(),
             test_input: self.test_input.clone(),
             matches: match_details.is_some(),
             match_details,
@@ -90,7 +98,7 @@ mod tests {
 
     #[test]
     fn simple_match() {
-        let cmd = TestCommand::new("123");
+        let cmd = TestCommand::new("123", Flavor::Rust);
         let gen = make_generated(r"\d+");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -100,7 +108,7 @@ mod tests {
 
     #[test]
     fn no_match() {
-        let cmd = TestCommand::new("abc");
+        let cmd = TestCommand::new("abc", Flavor::Rust);
         let gen = make_generated(r"\d+");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(!result.matches);
@@ -109,7 +117,7 @@ mod tests {
 
     #[test]
     fn partial_match_in_string() {
-        let cmd = TestCommand::new("abc123def");
+        let cmd = TestCommand::new("abc123def", Flavor::Rust);
         let gen = make_generated(r"\d+");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -121,7 +129,7 @@ mod tests {
 
     #[test]
     fn capture_groups_unnamed() {
-        let cmd = TestCommand::new("123-456");
+        let cmd = TestCommand::new("123-456", Flavor::Rust);
         let gen = make_generated(r"(\d+)-(\d+)");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -136,7 +144,7 @@ mod tests {
 
     #[test]
     fn named_capture_groups() {
-        let cmd = TestCommand::new("123");
+        let cmd = TestCommand::new("123", Flavor::Rust);
         let gen = make_generated(r"(?P<digits>\d+)");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -148,7 +156,7 @@ mod tests {
 
     #[test]
     fn mixed_named_and_unnamed_groups() {
-        let cmd = TestCommand::new("abc-123");
+        let cmd = TestCommand::new("abc-123", Flavor::Rust);
         let gen = make_generated(r"([a-z]+)-(?P<num>\d+)");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -162,7 +170,7 @@ mod tests {
 
     #[test]
     fn match_position_at_start() {
-        let cmd = TestCommand::new("123abc");
+        let cmd = TestCommand::new("123abc", Flavor::Rust);
         let gen = make_generated(r"\d+");
         let result = cmd.test_pattern(&gen).unwrap();
         let details = result.match_details.unwrap();
@@ -172,7 +180,7 @@ mod tests {
 
     #[test]
     fn match_position_at_end() {
-        let cmd = TestCommand::new("abc123");
+        let cmd = TestCommand::new("abc123", Flavor::Rust);
         let gen = make_generated(r"\d+");
         let result = cmd.test_pattern(&gen).unwrap();
         let details = result.match_details.unwrap();
@@ -182,7 +190,7 @@ mod tests {
 
     #[test]
     fn full_string_match() {
-        let cmd = TestCommand::new("hello");
+        let cmd = TestCommand::new("hello", Flavor::Rust);
         let gen = make_generated(r"^hello$");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -194,7 +202,7 @@ mod tests {
 
     #[test]
     fn anchored_no_match() {
-        let cmd = TestCommand::new("say hello");
+        let cmd = TestCommand::new("say hello", Flavor::Rust);
         let gen = make_generated(r"^hello$");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(!result.matches);
@@ -202,7 +210,7 @@ mod tests {
 
     #[test]
     fn invalid_regex_returns_error() {
-        let cmd = TestCommand::new("test");
+        let cmd = TestCommand::new("test", Flavor::Rust);
         let gen = make_generated(r"["); // unclosed bracket
         let result = cmd.test_pattern(&gen);
         assert!(result.is_err());
@@ -210,7 +218,7 @@ mod tests {
 
     #[test]
     fn invalid_regex_unbalanced_parens() {
-        let cmd = TestCommand::new("test");
+        let cmd = TestCommand::new("test", Flavor::Rust);
         let gen = make_generated(r"(abc");
         let result = cmd.test_pattern(&gen);
         assert!(result.is_err());
@@ -218,7 +226,7 @@ mod tests {
 
     #[test]
     fn empty_pattern_matches_empty() {
-        let cmd = TestCommand::new("");
+        let cmd = TestCommand::new("", Flavor::Rust);
         let gen = make_generated(r"");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -228,7 +236,7 @@ mod tests {
 
     #[test]
     fn empty_pattern_matches_at_start() {
-        let cmd = TestCommand::new("abc");
+        let cmd = TestCommand::new("abc", Flavor::Rust);
         let gen = make_generated(r"");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -239,7 +247,7 @@ mod tests {
 
     #[test]
     fn no_groups_empty_groups_vec() {
-        let cmd = TestCommand::new("abc");
+        let cmd = TestCommand::new("abc", Flavor::Rust);
         let gen = make_generated(r"abc");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -249,7 +257,7 @@ mod tests {
 
     #[test]
     fn unicode_in_input() {
-        let cmd = TestCommand::new("café");
+        let cmd = TestCommand::new("café", Flavor::Rust);
         let gen = make_generated(r"\w+");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -257,7 +265,7 @@ mod tests {
 
     #[test]
     fn test_result_has_pattern() {
-        let cmd = TestCommand::new("123");
+        let cmd = TestCommand::new("123", Flavor::Rust);
         let gen = make_generated(r"\d+");
         let result = cmd.test_pattern(&gen).unwrap();
         assert_eq!(result.pattern, r"\d+");
@@ -265,7 +273,7 @@ mod tests {
 
     #[test]
     fn test_result_has_input() {
-        let cmd = TestCommand::new("123");
+        let cmd = TestCommand::new("123", Flavor::Rust);
         let gen = make_generated(r"\d+");
         let result = cmd.test_pattern(&gen).unwrap();
         assert_eq!(result.test_input, "123");
@@ -273,7 +281,7 @@ mod tests {
 
     #[test]
     fn test_result_has_generated_response() {
-        let cmd = TestCommand::new("123");
+        let cmd = TestCommand::new("123", Flavor::Rust);
         let gen = make_generated(r"\d+");
         let result = cmd.test_pattern(&gen).unwrap();
         assert_eq!(result.generated.explanation, "test pattern");
@@ -281,7 +289,7 @@ mod tests {
 
     #[test]
     fn optional_group_not_matched() {
-        let cmd = TestCommand::new("abc");
+        let cmd = TestCommand::new("abc", Flavor::Rust);
         let gen = make_generated(r"abc(\d+)?");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -293,7 +301,7 @@ mod tests {
 
     #[test]
     fn alternation_first_branch() {
-        let cmd = TestCommand::new("cat");
+        let cmd = TestCommand::new("cat", Flavor::Rust);
         let gen = make_generated(r"cat|dog");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -302,7 +310,7 @@ mod tests {
 
     #[test]
     fn alternation_second_branch() {
-        let cmd = TestCommand::new("dog");
+        let cmd = TestCommand::new("dog", Flavor::Rust);
         let gen = make_generated(r"cat|dog");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
@@ -311,10 +319,28 @@ mod tests {
 
     #[test]
     fn email_like_pattern() {
-        let cmd = TestCommand::new("test@example.com");
+        let cmd = TestCommand::new("test@example.com", Flavor::Rust);
         let gen = make_generated(r"[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}");
         let result = cmd.test_pattern(&gen).unwrap();
         assert!(result.matches);
         assert_eq!(result.match_details.unwrap().full_match, "test@example.com");
     }
+
+    #[test]
+    fn backreference_with_pcre_engine() {
+        let cmd = TestCommand::new("abab", Flavor::Pcre);
+        let gen = make_generated(r"(?<pair>ab)\k<pair>");
+        let result = cmd.test_pattern(&gen).unwrap();
+        assert_eq!(result.engine, "fancy-regex");
+        let details = result.match_details.unwrap();
+        assert_eq!(details.full_match, "abab");
+        assert_eq!(details.groups[0].name, Some("pair".to_string()));
+    }
+
+    #[test]
+    fn invalid_for_flavor_names_engine() {
+        let cmd = TestCommand::new("123", Flavor::Posix);
+        let err = cmd.test_pattern(&make_generated(r"\d+")).unwrap_err();
+        assert!(err.to_string().contains("posix-ere"));
+    }
 }
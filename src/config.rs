@@ -0,0 +1,202 @@
+use crate::backend::BackendKind;
+use crate::error::{Error, Result};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// User configuration read from `$XDG_CONFIG_HOME/rgx/config.toml`.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Backend used when `--backend` is not given
+    pub backend: Option<BackendKind>,
+    /// Model used when neither `--model` nor `RGX_MODEL` is set
+    pub model: Option<String>,
+    /// Seconds a cached response stays valid
+    pub cache_ttl: Option<u64>,
+    /// Seconds a backend call may take
+    pub timeout: Option<u64>,
+    pub claude: ClaudeConfig,
+    pub anthropic: AnthropicConfig,
+    pub openai: OpenAiConfig,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClaudeConfig {
+    /// Program to run instead of `claude` from `PATH`
+    pub bin: Option<String>,
+    /// Extra arguments passed to every invocation, e.g. `--max-turns`
+    pub args: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnthropicConfig {
+    pub api_key: Option<String>,
+    pub base_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct OpenAiConfig {
+    pub api_key: Option<String>,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+}
+
+impl Config {
+    /// Load the config file, falling back to defaults when it does not exist.
+    pub fn load() -> Result<Config> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Config> {
+        let contents = fs::read_to_string(path)?;
+        toml::from_str(&contents).map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Configured model for `kind`. `openai.model` takes precedence over the
+    /// top-level `model`, since local model names rarely match hosted ones.
+    pub fn model_for(&self, kind: BackendKind) -> Option<&str> {
+        match kind {
+            BackendKind::Openai => self.openai.model.as_deref().or(self.model.as_deref()),
+            _ => self.model.as_deref(),
+        }
+    }
+
+    /// Location of the config file. `RGX_CONFIG` overrides the XDG location.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("RGX_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        xdg_dir("XDG_CONFIG_HOME", ".config").map(|dir| dir.join("config.toml"))
+    }
+}
+
+/// rgx's directory under an XDG base directory, e.g. `$XDG_CACHE_HOME/rgx`,
+/// falling back to `$HOME/<fallback>/rgx`.
+pub fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))
+        .map(|dir| dir.join("rgx"))
+}
+
+/// Read a non-empty environment variable.
+pub fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config_is_default() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn parse_anthropic_section() {
+        let config: Config = toml::from_str(
+            r#"
+backend = "anthropic"
+
+[anthropic]
+api_key = "sk-test"
+base_url = "http://127.0.0.1:8080"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.backend, Some(BackendKind::Anthropic));
+        assert_eq!(config.anthropic.api_key.as_deref(), Some("sk-test"));
+        assert_eq!(
+            config.anthropic.base_url.as_deref(),
+            Some("http://127.0.0.1:8080")
+        );
+    }
+
+    #[test]
+    fn parse_openai_section() {
+        let config: Config = toml::from_str(
+            r#"
+backend = "openai"
+
+[openai]
+base_url = "http://localhost:8080/v1"
+model = "qwen2.5-coder"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.backend, Some(BackendKind::Openai));
+        assert_eq!(
+            config.openai.base_url.as_deref(),
+            Some("http://localhost:8080/v1")
+        );
+        assert_eq!(config.openai.model.as_deref(), Some("qwen2.5-coder"));
+        assert!(config.openai.api_key.is_none());
+    }
+
+    #[test]
+    fn model_for_prefers_openai_section() {
+        let config: Config = toml::from_str(
+            r#"
+model = "sonnet"
+
+[openai]
+model = "qwen2.5-coder"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.model_for(BackendKind::Claude), Some("sonnet"));
+        assert_eq!(config.model_for(BackendKind::Anthropic), Some("sonnet"));
+        assert_eq!(config.model_for(BackendKind::Openai), Some("qwen2.5-coder"));
+    }
+
+    #[test]
+    fn parse_claude_section() {
+        let config: Config = toml::from_str(
+            r#"
+[claude]
+bin = "/opt/claude-1.0/bin/claude"
+args = ["--max-turns", "2", "--append-system-prompt", "Prefer short patterns."]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.claude.bin.as_deref(),
+            Some("/opt/claude-1.0/bin/claude")
+        );
+        assert_eq!(config.claude.args.len(), 4);
+        assert_eq!(config.claude.args[1], "2");
+    }
+
+    #[test]
+    fn parse_cache_ttl() {
+        let config: Config = toml::from_str("cache_ttl = 3600").unwrap();
+        assert_eq!(config.cache_ttl, Some(3600));
+    }
+
+    #[test]
+    fn unknown_keys_rejected() {
+        let result: std::result::Result<Config, _> = toml::from_str("colour = true");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn load_from_reports_path_on_error() {
+        let path = env::temp_dir().join(format!("rgx-config-test-{}.toml", std::process::id()));
+        fs::write(&path, "backend = [").unwrap();
+        let err = Config::load_from(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(err, Error::Config(_)));
+        assert!(err.to_string().contains("rgx-config-test"));
+    }
+}
@@ -0,0 +1,563 @@
+//! Local regex engines, one per flavor, so a pattern is compiled and tested
+//! the way its target would run it rather than always by Rust's rules.
+//!
+//! Rust and Go patterns use the `regex` crate itself, which shares RE2's
+//! design. JavaScript, PCRE, Python, Java and .NET need lookaround and
+//! backreferences, which only a backtracking engine offers, so they use
+//! `fancy-regex`. POSIX patterns are first checked for strict ERE syntax,
+//! then rewritten for the `regex` crate, which agrees with ERE on whether a
+//! string matches. BRE patterns are rewritten as ERE first and, since they
+//! allow backreferences, run on `fancy-regex`. Both engines stop at the
+//! first alternative that matches, where POSIX takes the longest match, so
+//! POSIX match spans are found by trying each end from the longest down.
+//!
+//! An engine that accepts more than its flavor, such as `fancy-regex`
+//! parsing `\p{L}` for Python, would pass patterns the flavor rejects, so
+//! every pattern must also pass the flavor's static check.
+
+use crate::check;
+use crate::error::{Error, Result};
+use crate::flavor::Flavor;
+use crate::syntax::basic_to_extended;
+use std::fmt::Display;
+use std::ops::Range;
+
+/// Class names POSIX defines for `[[:name:]]`.
+const POSIX_CLASSES: [&str; 12] = [
+    "alnum", "alpha", "blank", "cntrl", "digit", "graph", "lower", "print", "punct", "space",
+    "upper", "xdigit",
+];
+
+/// Characters ERE allows after a backslash: exactly its special characters.
+const POSIX_ESCAPABLE: &str = r"^.[]$()|*+?{}\";
+
+/// Name of the engine that runs `flavor` patterns, as shown to the user.
+pub fn name(flavor: Flavor) -> &'static str {
+    match flavor {
+        Flavor::Rust | Flavor::Go => "regex",
+        Flavor::Js | Flavor::Pcre | Flavor::Python | Flavor::Java | Flavor::Dotnet => "fancy-regex",
+        Flavor::Posix => "posix-ere",
+        Flavor::Bre => "posix-bre",
+    }
+}
+
+enum Compiled {
+    Regex(regex::Regex),
+    Fancy(fancy_regex::Regex),
+}
+
+/// A pattern compiled by the engine for its flavor.
+pub struct Engine {
+    compiled: Compiled,
+    /// POSIX patterns only: copies that must match up to the end of the
+    /// text, for finding the longest match
+    longest: Option<Longest>,
+    flavor: Flavor,
+}
+
+/// A POSIX pattern anchored with `\z`, once for text searched to its real
+/// end and once for text cut short, where `$` never matches.
+struct Longest {
+    whole: Compiled,
+    cut: Compiled,
+}
+
+impl Engine {
+    /// Compile `pattern` for `flavor`. Constructs the flavor does not
+    /// support are errors even when the engine accepts them.
+    pub fn compile(pattern: &str, flavor: Flavor) -> Result<Engine> {
+        let invalid = |message: &dyn Display| Error::InvalidRegex {
+            engine: name(flavor),
+            message: message.to_string(),
+        };
+        let mut longest = None;
+        let compiled = match flavor {
+            Flavor::Rust | Flavor::Go => {
+                Compiled::Regex(regex::Regex::new(pattern).map_err(|e| invalid(&e))?)
+            }
+            Flavor::Js | Flavor::Pcre | Flavor::Python | Flavor::Java | Flavor::Dotnet => {
+                Compiled::Fancy(fancy_regex::Regex::new(pattern).map_err(|e| {
+                    if engine_gap(&e) {
+                        Error::UnsupportedRegex {
+                            engine: name(flavor),
+                            message: e.to_string(),
+                        }
+                    } else {
+                        invalid(&e)
+                    }
+                })?)
+            }
+            Flavor::Posix | Flavor::Bre => {
+                let extended = match flavor {
+                    Flavor::Bre => basic_to_extended(pattern).map_err(|e| invalid(&e))?,
+                    _ => pattern.to_string(),
+                };
+                let build = |end: &str, anchored: bool| -> Result<Compiled> {
+                    let mut rewritten =
+                        posix_to_rust(&extended, flavor, end).map_err(|e| invalid(&e))?;
+                    if anchored {
+                        rewritten = format!(r"(?:{})\z", rewritten);
+                    }
+                    Ok(match flavor {
+                        Flavor::Bre => Compiled::Fancy(
+                            fancy_regex::Regex::new(&rewritten).map_err(|e| invalid(&e))?,
+                        ),
+                        _ => {
+                            Compiled::Regex(regex::Regex::new(&rewritten).map_err(|e| invalid(&e))?)
+                        }
+                    })
+                };
+                let compiled = build("$", false)?;
+                longest = Some(Longest {
+                    whole: build("$", true)?,
+                    cut: build(r"[^\s\S]", true)?,
+                });
+                compiled
+            }
+        };
+        if let Some(issue) = check::check(pattern, flavor).first() {
+            return Err(invalid(&issue.message));
+        }
+        Ok(Engine {
+            compiled,
+            longest,
+            flavor,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        name(self.flavor)
+    }
+
+    /// Whether `text` contains a match. A backtracking engine that gives up
+    /// on a pathological pattern counts as no match.
+    pub fn is_match(&self, text: &str) -> bool {
+        match &self.compiled {
+            Compiled::Regex(re) => re.is_match(text),
+            Compiled::Fancy(re) => re.is_match(text).unwrap_or(false),
+        }
+    }
+
+    /// Spans of the first match (index 0) and of each capture group within
+    /// it, `None` for groups that took no part.
+    pub fn captures(&self, text: &str) -> Option<Vec<Option<Range<usize>>>> {
+        let first = captures_at(&self.compiled, text, 0)?;
+        let Some(longest) = &self.longest else {
+            return Some(first);
+        };
+        // The leftmost start is the same either way; POSIX then wants the
+        // longest match from it
+        let Range { start, end } = first[0].clone()?;
+        (end + 1..=text.len())
+            .rev()
+            .filter(|&cut| text.is_char_boundary(cut))
+            .find_map(|cut| {
+                let anchored = if cut == text.len() {
+                    &longest.whole
+                } else {
+                    &longest.cut
+                };
+                captures_at(anchored, &text[..cut], start)
+                    .filter(|spans| spans[0].as_ref().is_some_and(|m| m.start == start))
+            })
+            .or(Some(first))
+    }
+
+    /// Name of each capture group by index, starting with the whole match.
+    pub fn capture_names(&self) -> Vec<Option<&str>> {
+        match &self.compiled {
+            Compiled::Regex(re) => re.capture_names().collect(),
+            Compiled::Fancy(re) => re.capture_names().collect(),
+        }
+    }
+}
+
+/// Whether `fancy-regex` rejected a pattern for a feature it lacks rather
+/// than for being malformed: escapes like JavaScript's `\cJ`, classes like
+/// `[^]`, unknown group flags and lookbehind of unbounded length.
+fn engine_gap(error: &fancy_regex::Error) -> bool {
+    use fancy_regex::{CompileError, ParseError};
+    match error {
+        fancy_regex::Error::ParseError(_, kind) => matches!(
+            kind,
+            ParseError::InvalidEscape(_)
+                | ParseError::InvalidClass
+                | ParseError::UnknownFlag(_)
+                | ParseError::ChangingUnicodeModeUnsupported
+        ),
+        fancy_regex::Error::CompileError(kind) => matches!(
+            **kind,
+            CompileError::LookBehindNotConst
+                | CompileError::VariableLookBehindRequiresFeature
+                | CompileError::FeatureNotYetSupported(_)
+                | CompileError::NamedBackrefOnly
+        ),
+        _ => false,
+    }
+}
+
+/// Spans of the first match in `text` that starts at or after `start`.
+fn captures_at(compiled: &Compiled, text: &str, start: usize) -> Option<Vec<Option<Range<usize>>>> {
+    match compiled {
+        Compiled::Regex(re) => re
+            .captures_at(text, start)
+            .map(|caps| caps.iter().map(|m| m.map(|m| m.range())).collect()),
+        Compiled::Fancy(re) => re
+            .captures_from_pos(text, start)
+            .ok()
+            .flatten()
+            .map(|caps| caps.iter().map(|m| m.map(|m| m.range())).collect()),
+    }
+}
+
+/// Check that `pattern` is strict POSIX ERE and rewrite it for the `regex`
+/// crate, whose bracket expressions give `\`, `[`, `--`, `&&` and `~~` meanings
+/// that POSIX does not. `\1` to `\9` are allowed when `flavor` has
+/// backreferences. The `$` anchor is written as `end`.
+fn posix_to_rust(pattern: &str, flavor: Flavor, end: &str) -> std::result::Result<String, String> {
+    let backreferences = flavor.capabilities().backreferences;
+    let chars: Vec<(usize, char)> = pattern.char_indices().collect();
+    let mut out = String::with_capacity(pattern.len());
+    let mut quantified = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (at, c) = chars[i];
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        let mut quantifier = false;
+        match c {
+            '\\' => {
+                let escaped = next.ok_or_else(|| format!("trailing \\ at offset {}", at))?;
+                let backreference = backreferences && ('1'..='9').contains(&escaped);
+                if !POSIX_ESCAPABLE.contains(escaped) && !backreference {
+                    return Err(format!(
+                        "\\{} at offset {} is not {}{}",
+                        escaped,
+                        at,
+                        flavor.label(),
+                        shorthand_hint(escaped)
+                    ));
+                }
+                out.push(c);
+                out.push(escaped);
+                i += 1;
+            }
+            '(' if next == Some('?') => {
+                return Err(format!(
+                    "(? at offset {} is not {}: non-capturing, named and lookaround groups need another flavor",
+                    at,
+                    flavor.label()
+                ));
+            }
+            '*' | '+' | '?' if quantified => {
+                return Err(format!(
+                    "{} at offset {} follows another quantifier: lazy and possessive quantifiers are not {}",
+                    c,
+                    at,
+                    flavor.label()
+                ));
+            }
+            '*' | '+' | '?' => {
+                out.push(c);
+                quantifier = true;
+            }
+            '{' => {
+                let close = interval_end(&chars, i)
+                    .ok_or_else(|| format!("malformed interval at offset {}", at))?;
+                if quantified {
+                    return Err(format!(
+                        "interval at offset {} follows another quantifier",
+                        at
+                    ));
+                }
+                out.extend(chars[i..=close].iter().map(|&(_, c)| c));
+                quantifier = true;
+                i = close;
+            }
+            '[' => {
+                i = bracket(&chars, i, &mut out)?;
+            }
+            '$' => out.push_str(end),
+            _ => out.push(c),
+        }
+        quantified = quantifier;
+        i += 1;
+    }
+    Ok(out)
+}
+
+fn shorthand_hint(escaped: char) -> &'static str {
+    match escaped {
+        'd' | 'D' => "; use [[:digit:]] or [0-9]",
+        'w' | 'W' => "; use [[:alnum:]_]",
+        's' | 'S' => "; use [[:space:]]",
+        _ => "",
+    }
+}
+
+/// Index of the `}` closing an interval `{n}`, `{n,}` or `{n,m}` that
+/// opens at `start`.
+fn interval_end(chars: &[(usize, char)], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    let digits = |i: &mut usize| {
+        let from = *i;
+        while chars.get(*i).is_some_and(|(_, c)| c.is_ascii_digit()) {
+            *i += 1;
+        }
+        *i > from
+    };
+    if !digits(&mut i) {
+        return None;
+    }
+    if chars.get(i).map(|&(_, c)| c) == Some(',') {
+        i += 1;
+        digits(&mut i);
+    }
+    (chars.get(i).map(|&(_, c)| c) == Some('}')).then_some(i)
+}
+
+/// Copy the bracket expression opening at `start` to `out`, escaping what
+/// the `regex` crate would read differently. Returns the index of its `]`.
+fn bracket(
+    chars: &[(usize, char)],
+    start: usize,
+    out: &mut String,
+) -> std::result::Result<usize, String> {
+    let char_at = |i: usize| chars.get(i).map(|&(_, c)| c);
+    let unclosed = || format!("unclosed bracket expression at offset {}", chars[start].0);
+    out.push('[');
+    let mut i = start + 1;
+    if char_at(i) == Some('^') {
+        out.push('^');
+        i += 1;
+    }
+    let first = i;
+    // A leading ] is a literal member
+    if char_at(i) == Some(']') {
+        out.push_str(r"\]");
+        i += 1;
+    }
+    // Whether the last member was a single character a range could start at
+    let mut endpoint = i > first;
+
+    loop {
+        let (at, c) = *chars.get(i).ok_or_else(unclosed)?;
+        match c {
+            ']' => {
+                out.push(']');
+                return Ok(i);
+            }
+            '[' if matches!(char_at(i + 1), Some(':' | '=' | '.')) => {
+                let delimiter = char_at(i + 1).unwrap_or_default();
+                let close = (i + 2..chars.len().saturating_sub(1))
+                    .find(|&j| char_at(j) == Some(delimiter) && char_at(j + 1) == Some(']'))
+                    .ok_or_else(unclosed)?;
+                let name: String = chars[i + 2..close].iter().map(|&(_, c)| c).collect();
+                if delimiter != ':' {
+                    return Err(format!(
+                        "[{d}{}{d}] at offset {} is valid POSIX but cannot be checked locally",
+                        name,
+                        at,
+                        d = delimiter
+                    ));
+                }
+                if !POSIX_CLASSES.contains(&name.as_str()) {
+                    return Err(format!("unknown class [:{}:] at offset {}", name, at));
+                }
+                out.push_str(&format!("[:{}:]", name));
+                endpoint = false;
+                i = close + 1;
+            }
+            '-' if endpoint && char_at(i + 1).is_some_and(|c| c != ']') => {
+                // A range, whose end may itself be a character to escape
+                out.push('-');
+                i += 1;
+                push_member(out, chars[i].1);
+                endpoint = false;
+            }
+            _ => {
+                push_member(out, c);
+                endpoint = true;
+            }
+        }
+        i += 1;
+    }
+}
+
+/// Write a literal bracket member, escaping the characters POSIX reads
+/// literally but a `regex` crate class does not: `\`, `[`, and `-`, `&`
+/// and `~`, which double up into class operators.
+fn push_member(out: &mut String, c: char) {
+    if matches!(c, '\\' | '[' | '-' | '&' | '~') {
+        out.push('\\');
+    }
+    out.push(c);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile_error(pattern: &str, flavor: Flavor) -> String {
+        match Engine::compile(pattern, flavor) {
+            Err(Error::InvalidRegex { message, .. }) => message,
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("{:?} compiled for {}", pattern, flavor),
+        }
+    }
+
+    #[test]
+    fn each_flavor_names_its_engine() {
+        assert_eq!(name(Flavor::Rust), "regex");
+        assert_eq!(name(Flavor::Pcre), "fancy-regex");
+        assert_eq!(
+            Engine::compile("a", Flavor::Posix).unwrap().name(),
+            "posix-ere"
+        );
+    }
+
+    #[test]
+    fn lookbehind_compiles_for_pcre_and_js_only() {
+        for flavor in [Flavor::Pcre, Flavor::Js] {
+            let engine = Engine::compile(r"(?<=\$)\d+", flavor).unwrap();
+            assert!(engine.is_match("$42"));
+            assert!(!engine.is_match("42"));
+        }
+        compile_error(r"(?<=\$)\d+", Flavor::Rust);
+        compile_error(r"(?<=\$)[0-9]+", Flavor::Posix);
+    }
+
+    #[test]
+    fn engine_gaps_are_not_invalid_patterns() {
+        for pattern in [r"[^]", r"\cJ"] {
+            assert!(matches!(
+                Engine::compile(pattern, Flavor::Js),
+                Err(Error::UnsupportedRegex { .. })
+            ));
+        }
+        compile_error(r"(\d+", Flavor::Js);
+    }
+
+    #[test]
+    fn backreferences_for_pcre() {
+        let engine = Engine::compile(r"^(\w)\1$", Flavor::Pcre).unwrap();
+        assert!(engine.is_match("aa"));
+        assert!(!engine.is_match("ab"));
+    }
+
+    #[test]
+    fn posix_rejects_perl_syntax() {
+        assert!(compile_error(r"\d+", Flavor::Posix).contains("[[:digit:]]"));
+        assert!(compile_error(r"(?:ab)+", Flavor::Posix).contains("offset 0"));
+        assert!(compile_error(r"a+?", Flavor::Posix).contains("lazy"));
+        assert!(compile_error(r"a{2}+", Flavor::Posix).contains("quantifier"));
+        assert!(compile_error(r"a{,3}", Flavor::Posix).contains("interval"));
+        assert!(compile_error(r"\bword", Flavor::Posix).contains(r"\b"));
+        assert!(compile_error("[[:letter:]]", Flavor::Posix).contains("unknown class"));
+        assert!(compile_error("[abc", Flavor::Posix).contains("unclosed"));
+    }
+
+    #[test]
+    fn posix_matches_are_leftmost_longest() {
+        let whole = |pattern: &str, flavor: Flavor, text: &str| {
+            Engine::compile(pattern, flavor)
+                .unwrap()
+                .captures(text)
+                .unwrap()[0]
+                .clone()
+        };
+        assert_eq!(whole("a|ab", Flavor::Posix, "xab"), Some(1..3));
+        assert_eq!(whole("a|ab", Flavor::Rust, "xab"), Some(1..2));
+        assert_eq!(whole("(a|ab)(c|bcd)", Flavor::Posix, "abcd"), Some(0..4));
+        // $ matches only at the real end of the text
+        assert_eq!(whole("a$|ab", Flavor::Posix, "abc"), Some(0..2));
+        assert_eq!(whole("ab|a$", Flavor::Posix, "a"), Some(0..1));
+        assert_eq!(whole(r"\(a\)\1*", Flavor::Bre, "aaab"), Some(0..3));
+        assert_eq!(whole("é|éé", Flavor::Posix, "ééx"), Some(0..4));
+
+        let spans = Engine::compile("(a|ab)(b*)", Flavor::Posix)
+            .unwrap()
+            .captures("abb")
+            .unwrap();
+        assert_eq!(spans[0], Some(0..3));
+    }
+
+    #[test]
+    fn posix_bracket_dashes_stay_literal() {
+        // [!--] is the range ! to -, not a regex crate class subtraction
+        let engine = Engine::compile("^[!--]$", Flavor::Posix).unwrap();
+        assert!(engine.is_match("-"));
+        assert!(engine.is_match(","));
+        assert!(!engine.is_match("."));
+
+        let engine = Engine::compile("^[-a-c-]+$", Flavor::Posix).unwrap();
+        assert!(engine.is_match("-b-"));
+        assert!(!engine.is_match("d"));
+        assert!(Engine::compile("^[*--]$", Flavor::Bre)
+            .unwrap()
+            .is_match("-"));
+    }
+
+    #[test]
+    fn posix_bracket_expressions_are_literal() {
+        let engine = Engine::compile(r"^[\d]+$", Flavor::Posix).unwrap();
+        assert!(engine.is_match(r"\d"));
+        assert!(!engine.is_match("42"));
+        let engine = Engine::compile("^[]a&&[]+$", Flavor::Posix).unwrap();
+        assert!(engine.is_match("]&["));
+        let engine = Engine::compile("^[[:digit:]]{3}-[^[:space:]]+$", Flavor::Posix).unwrap();
+        assert!(engine.is_match("555-1234"));
+        assert!(!engine.is_match("555- 1"));
+    }
+
+    #[test]
+    fn posix_escapes_special_characters() {
+        let engine = Engine::compile(r"^a\.b\{1\}$", Flavor::Posix).unwrap();
+        assert!(engine.is_match("a.b{1}"));
+    }
+
+    #[test]
+    fn bre_groups_are_escaped() {
+        let engine = Engine::compile(r"^\(ab\)\{2\}+\1$", Flavor::Bre).unwrap();
+        assert_eq!(engine.name(), "posix-bre");
+        assert!(engine.is_match("abab+ab"));
+        assert!(!engine.is_match("abab"));
+        let engine = Engine::compile("^*a[|]b?$", Flavor::Bre).unwrap();
+        assert!(engine.is_match("*a|b?"));
+        assert!(!engine.is_match("a"));
+    }
+
+    #[test]
+    fn bre_rejects_gnu_and_perl_syntax() {
+        assert!(compile_error(r"a\+", Flavor::Bre).contains("GNU extension"));
+        assert!(compile_error(r"\d", Flavor::Bre).contains("is not POSIX BRE"));
+        assert!(compile_error(r"\1", Flavor::Posix).contains("is not POSIX ERE"));
+    }
+
+    #[test]
+    fn new_flavors_use_matching_engines() {
+        let engine = Engine::compile(r"(?P<word>\w+) (?P=word)", Flavor::Python).unwrap();
+        assert!(engine.is_match("hey hey"));
+        compile_error(r"(\w)\1", Flavor::Go);
+        let engine = Engine::compile(r"(?<=@)\w++", Flavor::Java).unwrap();
+        assert!(engine.is_match("a@b"));
+    }
+
+    #[test]
+    fn flavor_check_applies_to_permissive_engines() {
+        let message = compile_error(r"(?<n>x)", Flavor::Python);
+        assert!(message.contains("named group (?<n> is not supported by Python re"));
+        assert!(compile_error(r"\p{L}", Flavor::Python).contains("Unicode property class"));
+        assert!(Engine::compile(r"(?P<n>x)", Flavor::Python).is_ok());
+    }
+
+    #[test]
+    fn captures_report_group_spans() {
+        for flavor in [Flavor::Rust, Flavor::Pcre] {
+            let engine = Engine::compile(r"(?<year>\d{4})-(\d{2})?", flavor).unwrap();
+            let caps = engine.captures("on 2024-").unwrap();
+            assert_eq!(caps, vec![Some(3..8), Some(3..7), None]);
+            assert_eq!(engine.capture_names(), vec![None, Some("year"), None]);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -5,6 +6,27 @@ pub enum Error {
     #[error("Claude CLI error: {0}")]
     Claude(String),
 
+    #[error("HTTP error: {0}")]
+    Http(String),
+
+    #[error("API error ({status}): {message}")]
+    Api { status: u16, message: String },
+
+    #[error("Timed out after {}s waiting for the backend", .0.as_secs_f64())]
+    Timeout(Duration),
+
+    #[error("Interrupted")]
+    Interrupted,
+
+    #[error("Session error: {0}")]
+    Session(String),
+
+    #[error("Fixture error: {0}")]
+    Fixture(String),
+
+    #[error("Config error: {0}")]
+    Config(String),
+
     #[error("Failed to parse response: {0}")]
     Parse(#[from] serde_json::Error),
 
@@ -14,8 +36,17 @@ pub enum Error {
     #[error("Invalid flag combination: {0}")]
     InvalidFlags(String),
 
-    #[error("Invalid regex pattern: {0}")]
-    InvalidRegex(#[from] regex::Error),
+    #[error("Invalid regex pattern ({engine}): {message}")]
+    InvalidRegex {
+        engine: &'static str,
+        message: String,
+    },
+
+    #[error("Pattern not supported by {engine}: {message}")]
+    UnsupportedRegex {
+        engine: &'static str,
+        message: String,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,242 @@
+use crate::log;
+use std::fmt;
+
+/// How the JSON object was located in a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// The whole response is a JSON object
+    Whole,
+    /// The contents of a fenced code block
+    Fence,
+    /// The first balanced object that parses, anywhere in the text
+    Scan,
+    /// Nothing parsed: the first balanced object, or the trimmed response,
+    /// is passed on so the parse error points at the real problem
+    Fallback,
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Strategy::Whole => "whole response",
+            Strategy::Fence => "fenced code block",
+            Strategy::Scan => "balanced object scan",
+            Strategy::Fallback => "fallback",
+        })
+    }
+}
+
+/// The JSON object in a model response that may surround it with prose or
+/// markdown fences. Logs the strategy that found it.
+pub fn json(text: &str) -> &str {
+    let (json, strategy) = extract(text);
+    log::debug(format_args!("extracted JSON from {}:\n{}", strategy, json));
+    json
+}
+
+/// Try each strategy in turn, from the most to the least specific.
+pub fn extract(text: &str) -> (&str, Strategy) {
+    let trimmed = text.trim();
+    if is_object(trimmed) {
+        return (trimmed, Strategy::Whole);
+    }
+
+    if let Some(block) = fenced_blocks(text)
+        .into_iter()
+        .map(str::trim)
+        .find(|block| is_object(block))
+    {
+        return (block, Strategy::Fence);
+    }
+
+    if let Some(object) = objects(text).find(|object| is_object(object)) {
+        return (object, Strategy::Scan);
+    }
+
+    let fallback = objects(text).next().unwrap_or(trimmed);
+    (fallback, Strategy::Fallback)
+}
+
+fn is_object(s: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(s).is_ok_and(|value| value.is_object())
+}
+
+/// Contents of each fenced code block, whatever its language tag. A fence
+/// closes only on a line of at least as many backticks as opened it, so
+/// blocks nested inside a longer fence stay part of its contents. An
+/// unclosed fence runs to the end of the text.
+fn fenced_blocks(text: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(usize, usize)> = None;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let fence = line.trim();
+        let ticks = fence.chars().take_while(|&c| c == '`').count();
+
+        match open {
+            Some((width, content)) if ticks >= width && ticks == fence.len() => {
+                blocks.push(&text[content..start]);
+                open = None;
+            }
+            None if ticks >= 3 => open = Some((ticks, offset)),
+            _ => {}
+        }
+    }
+
+    if let Some((_, content)) = open {
+        blocks.push(&text[content.min(text.len())..]);
+    }
+    blocks
+}
+
+/// Every balanced `{...}` span in `text`, in order of where it starts.
+/// Braces inside JSON strings are ignored.
+fn objects(text: &str) -> impl Iterator<Item = &str> {
+    text.char_indices()
+        .filter(|&(_, c)| c == '{')
+        .filter_map(move |(start, _)| balanced(&text[start..]))
+}
+
+/// The object opening at the start of `text`, up to its matching brace.
+fn balanced(text: &str) -> Option<&str> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_json_is_whole() {
+        let input = "{\"key\": \"value\"}";
+        assert_eq!(extract(input), (input, Strategy::Whole));
+    }
+
+    #[test]
+    fn whitespace_is_trimmed() {
+        assert_eq!(extract("  {}\n"), ("{}", Strategy::Whole));
+    }
+
+    #[test]
+    fn json_code_block() {
+        let input = "```json\n{\"key\": \"value\"}\n```";
+        assert_eq!(extract(input), ("{\"key\": \"value\"}", Strategy::Fence));
+    }
+
+    #[test]
+    fn plain_and_other_language_blocks() {
+        assert_eq!(extract("```\n{\"a\": 1}\n```").0, "{\"a\": 1}");
+        assert_eq!(extract("```javascript\n{\"a\": 1}\n```").0, "{\"a\": 1}");
+        assert_eq!(extract("```JSON5\n{\"a\": 1}\n```").0, "{\"a\": 1}");
+    }
+
+    #[test]
+    fn multiline_block() {
+        let input = "```json\n{\n  \"a\": 1,\n  \"b\": 2\n}\n```";
+        assert_eq!(extract(input).0, "{\n  \"a\": 1,\n  \"b\": 2\n}");
+    }
+
+    #[test]
+    fn leading_prose_and_trailing_commentary() {
+        let input = "Here is the pattern:\n\n```json\n{\"pattern\": \"\\\\d+\"}\n```\n\nLet me know if you need changes!";
+        assert_eq!(
+            extract(input),
+            ("{\"pattern\": \"\\\\d+\"}", Strategy::Fence)
+        );
+    }
+
+    #[test]
+    fn first_valid_block_wins() {
+        let input = "```\nnot json\n```\n```json\n{\"a\": 1}\n```\n```json\n{\"a\": 2}\n```";
+        assert_eq!(extract(input), ("{\"a\": 1}", Strategy::Fence));
+    }
+
+    #[test]
+    fn nested_fences() {
+        let input = "````markdown\nSome notes\n```json\n{\"a\": 1}\n```\n````";
+        assert_eq!(extract(input), ("{\"a\": 1}", Strategy::Scan));
+        assert_eq!(
+            fenced_blocks(input),
+            vec!["Some notes\n```json\n{\"a\": 1}\n```\n"]
+        );
+    }
+
+    #[test]
+    fn unclosed_fence_runs_to_end() {
+        assert_eq!(
+            extract("```json\n{\"a\": 1}\n"),
+            ("{\"a\": 1}", Strategy::Fence)
+        );
+    }
+
+    #[test]
+    fn object_inside_prose() {
+        let input = "Sure! {\"pattern\": \"a{2}\", \"nested\": {\"b\": \"}\"}} Hope that helps.";
+        assert_eq!(
+            extract(input),
+            (
+                "{\"pattern\": \"a{2}\", \"nested\": {\"b\": \"}\"}}",
+                Strategy::Scan
+            )
+        );
+    }
+
+    #[test]
+    fn skips_braces_in_prose() {
+        let input = "Use {n} for counts: {\"pattern\": \"x{3}\"}";
+        assert_eq!(extract(input), ("{\"pattern\": \"x{3}\"}", Strategy::Scan));
+    }
+
+    #[test]
+    fn escaped_quotes_in_strings() {
+        let input = "Result: {\"pattern\": \"\\\"}\\\"\"} done";
+        assert_eq!(extract(input).0, "{\"pattern\": \"\\\"}\\\"\"}");
+    }
+
+    #[test]
+    fn invalid_object_falls_back_to_first_balanced() {
+        let input = "Here: {\"pattern\": \"\\d\",} thanks";
+        assert_eq!(
+            extract(input),
+            ("{\"pattern\": \"\\d\",}", Strategy::Fallback)
+        );
+    }
+
+    #[test]
+    fn plain_text_falls_back_to_input() {
+        assert_eq!(
+            extract(" just some text "),
+            ("just some text", Strategy::Fallback)
+        );
+        assert_eq!(extract("```json\n```").1, Strategy::Fallback);
+    }
+}
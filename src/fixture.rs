@@ -0,0 +1,269 @@
+use crate::backend::{Backend, Metadata, Reply};
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One recorded prompt and the reply it got.
+#[derive(Serialize, Deserialize, Debug)]
+struct Fixture {
+    prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schema: Option<Value>,
+    text: String,
+    metadata: Metadata,
+}
+
+/// Hex SHA-256 of the prompt and schema, used to name fixture files.
+/// Session ids are left out so a conversation replays however it was
+/// resumed.
+fn key(prompt: &str, schema: Option<&Value>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(prompt.as_bytes());
+    hasher.update([0]);
+    if let Some(schema) = schema {
+        hasher.update(schema.to_string().as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Fixture file for the next use of a prompt in `dir`. A prompt sent more
+/// than once in a run, such as a retry after an unparsable reply, gets one
+/// file per send, numbered in order, so no reply overwrites an earlier one.
+fn next_path(
+    dir: &Path,
+    sent: &RefCell<HashMap<String, usize>>,
+    prompt: &str,
+    schema: Option<&Value>,
+) -> PathBuf {
+    let key = key(prompt, schema);
+    let mut sent = sent.borrow_mut();
+    let seq = sent.entry(key.clone()).or_insert(0);
+    *seq += 1;
+    dir.join(format!("{}-{}.json", key, seq))
+}
+
+/// Backend wrapper that writes every prompt and its reply to a fixture
+/// directory for `Replay` to serve later.
+pub struct Recorder<B> {
+    inner: B,
+    dir: PathBuf,
+    sent: RefCell<HashMap<String, usize>>,
+}
+
+impl<B: Backend> Recorder<B> {
+    pub fn new(inner: B, dir: PathBuf) -> Self {
+        Recorder {
+            inner,
+            dir,
+            sent: RefCell::default(),
+        }
+    }
+
+    fn record(&self, prompt: &str, schema: Option<&Value>, reply: Result<Reply>) -> Result<Reply> {
+        let reply = reply?;
+        let fixture = Fixture {
+            prompt: prompt.to_string(),
+            schema: schema.cloned(),
+            text: reply.text.clone(),
+            metadata: reply.metadata.clone(),
+        };
+        // Unlike the cache, a recording that silently misses replies would
+        // only fail later at replay, so write errors fail the command
+        fs::create_dir_all(&self.dir)?;
+        fs::write(
+            next_path(&self.dir, &self.sent, prompt, schema),
+            serde_json::to_string_pretty(&fixture)?,
+        )?;
+        Ok(reply)
+    }
+}
+
+impl<B: Backend> Backend for Recorder<B> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn identity(&self) -> String {
+        self.inner.identity()
+    }
+
+    fn query(&self, prompt: &str) -> Result<Reply> {
+        self.record(prompt, None, self.inner.query(prompt))
+    }
+
+    fn resume(&self, session_id: &str, prompt: &str) -> Result<Reply> {
+        self.record(prompt, None, self.inner.resume(session_id, prompt))
+    }
+
+    fn query_structured(&self, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.record(
+            prompt,
+            Some(schema),
+            self.inner.query_structured(prompt, schema),
+        )
+    }
+
+    fn resume_structured(&self, session_id: &str, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.record(
+            prompt,
+            Some(schema),
+            self.inner.resume_structured(session_id, prompt, schema),
+        )
+    }
+
+    fn discard(&self, prompt: &str, schema: &Value) {
+        self.inner.discard(prompt, schema)
+    }
+
+    fn metadata(&self) -> Metadata {
+        self.inner.metadata()
+    }
+}
+
+/// Backend that answers from a directory written by `Recorder`, without
+/// any model. Replies carry the metadata they were recorded with, so output
+/// matches the original run exactly.
+pub struct Replay {
+    dir: PathBuf,
+    sent: RefCell<HashMap<String, usize>>,
+}
+
+impl Replay {
+    pub fn new(dir: PathBuf) -> Self {
+        Replay {
+            dir,
+            sent: RefCell::default(),
+        }
+    }
+
+    fn load(&self, prompt: &str, schema: Option<&Value>) -> Result<Reply> {
+        let path = next_path(&self.dir, &self.sent, prompt, schema);
+        let contents = fs::read_to_string(&path).map_err(|e| {
+            Error::Fixture(format!(
+                "no recorded reply for this prompt ({}: {}); record it again with --record",
+                path.display(),
+                e
+            ))
+        })?;
+        let fixture: Fixture = serde_json::from_str(&contents)
+            .map_err(|e| Error::Fixture(format!("{}: {}", path.display(), e)))?;
+        Ok(Reply {
+            text: fixture.text,
+            metadata: fixture.metadata,
+        })
+    }
+}
+
+impl Backend for Replay {
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    fn model(&self) -> &str {
+        "fixtures"
+    }
+
+    fn query(&self, prompt: &str) -> Result<Reply> {
+        self.load(prompt, None)
+    }
+
+    fn resume(&self, _session_id: &str, prompt: &str) -> Result<Reply> {
+        self.load(prompt, None)
+    }
+
+    fn query_structured(&self, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.load(prompt, Some(schema))
+    }
+
+    fn resume_structured(&self, _session_id: &str, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.load(prompt, Some(schema))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::MockBackend;
+    use serde_json::json;
+
+    #[test]
+    fn key_depends_on_prompt_and_schema() {
+        let schema = json!({"type": "object"});
+        let base = key("email", Some(&schema));
+        assert_eq!(base.len(), 64);
+        assert_ne!(base, key("emails", Some(&schema)));
+        assert_ne!(base, key("email", None));
+        assert_ne!(base, key("email", Some(&json!({"type": "string"}))));
+    }
+
+    #[test]
+    fn recorded_replies_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = json!({"type": "object"});
+        let recorder = Recorder::new(
+            MockBackend::new(["plain", "structured"]).with_session("abc"),
+            dir.path().to_path_buf(),
+        );
+        recorder.query("one").unwrap();
+        recorder.query_structured("two", &schema).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        let replay = Replay::new(dir.path().to_path_buf());
+        assert_eq!(replay.query("one").unwrap().text, "plain");
+        let reply = replay.resume_structured("other", "two", &schema).unwrap();
+        assert_eq!(reply.text, "structured");
+        assert_eq!(reply.metadata.backend, "mock");
+        assert_eq!(reply.metadata.session_id.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn repeated_prompts_replay_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = Recorder::new(
+            MockBackend::new(["first", "second"]),
+            dir.path().to_path_buf(),
+        );
+        recorder.query("same").unwrap();
+        recorder.query("same").unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        let replay = Replay::new(dir.path().to_path_buf());
+        assert_eq!(replay.query("same").unwrap().text, "first");
+        assert_eq!(replay.query("same").unwrap().text, "second");
+        assert!(matches!(replay.query("same"), Err(Error::Fixture(_))));
+    }
+
+    #[test]
+    fn errors_are_not_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = Recorder::new(
+            MockBackend::new(Vec::<String>::new()),
+            dir.path().join("fixtures"),
+        );
+        assert!(recorder.query("one").is_err());
+        assert!(!dir.path().join("fixtures").exists());
+    }
+
+    #[test]
+    fn missing_fixture_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = Replay::new(dir.path().to_path_buf())
+            .query("unrecorded")
+            .unwrap_err();
+        assert!(matches!(err, Error::Fixture(_)));
+        assert!(err.to_string().contains("--record"));
+    }
+}
@@ -0,0 +1,511 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Regex dialects rgx can target.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Flavor {
+    /// Rust's `regex` crate
+    #[default]
+    Rust,
+    /// ECMAScript (JavaScript) regular expressions
+    // Sessions saved before flavors had their own type spelled it out
+    #[serde(alias = "javascript")]
+    Js,
+    /// Perl-compatible regular expressions
+    Pcre,
+    /// POSIX extended regular expressions
+    Posix,
+    /// Python's `re` module
+    Python,
+    /// Go's `regexp` package (RE2 syntax)
+    #[value(alias = "re2")]
+    Go,
+    /// `java.util.regex`
+    Java,
+    /// .NET `System.Text.RegularExpressions`
+    Dotnet,
+    /// POSIX basic regular expressions, as in grep and sed without -E
+    Bre,
+}
+
+/// What a flavor's syntax supports. One table drives the generate and
+/// explain prompts, local verification, inference and colorizing, so they
+/// can never disagree about a flavor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `(?=...)` and `(?!...)`
+    pub lookahead: bool,
+    /// `(?<=...)` and `(?<!...)`
+    pub lookbehind: bool,
+    /// `\1` referring back to a group
+    pub backreferences: bool,
+    /// Accepted ways to write a named group, preferred first; empty when
+    /// groups cannot be named
+    pub named_groups: &'static [&'static str],
+    /// `a++`, `a*+`, `a?+`
+    pub possessive_quantifiers: bool,
+    /// `*?`, `+?`, `??`
+    pub lazy_quantifiers: bool,
+    /// `(?:...)`
+    pub non_capturing_groups: bool,
+    /// `(?i)` and `(?i:...)` flag groups
+    pub inline_flags: bool,
+    /// `(?>...)`
+    pub atomic_groups: bool,
+    /// `\d`, `\w`, `\s` and their negations
+    pub shorthand_classes: bool,
+    /// `\p{L}` and other Unicode property classes
+    pub unicode_classes: bool,
+    /// `[[:alpha:]]` and the other named classes inside brackets
+    pub posix_classes: bool,
+    /// `\n`, `\t`, `\x41` and other backslash-letter escapes
+    pub letter_escapes: bool,
+    /// `\` escapes the next character inside brackets; in POSIX it is an
+    /// ordinary member
+    pub bracket_escapes: bool,
+    /// `\b` and `\B`
+    pub word_boundaries: bool,
+    /// `\A`, anchoring to the start of the whole input rather than a line
+    pub string_anchors: bool,
+    /// How the anchor to the very end of the input is written: `\z`, or
+    /// `\Z` in Python
+    pub end_anchor: Option<&'static str>,
+    /// `\Z` matching at the end of the input or just before a final newline
+    pub newline_end_anchor: bool,
+    /// `(?R)` and other recursive patterns
+    pub recursion: bool,
+    /// POSIX basic syntax: groups and intervals are written `\(...\)` and
+    /// `\{m,n\}`, and `+`, `?` and `|` are ordinary characters
+    pub basic_syntax: bool,
+}
+
+const RUST: Capabilities = Capabilities {
+    lookahead: false,
+    lookbehind: false,
+    backreferences: false,
+    named_groups: &["(?<name>...)", "(?P<name>...)"],
+    possessive_quantifiers: false,
+    lazy_quantifiers: true,
+    non_capturing_groups: true,
+    inline_flags: true,
+    atomic_groups: false,
+    shorthand_classes: true,
+    unicode_classes: true,
+    posix_classes: true,
+    letter_escapes: true,
+    bracket_escapes: true,
+    word_boundaries: true,
+    string_anchors: true,
+    end_anchor: Some(r"\z"),
+    newline_end_anchor: false,
+    recursion: false,
+    basic_syntax: false,
+};
+
+const JS: Capabilities = Capabilities {
+    lookahead: true,
+    lookbehind: true,
+    backreferences: true,
+    named_groups: &["(?<name>...)"],
+    possessive_quantifiers: false,
+    lazy_quantifiers: true,
+    non_capturing_groups: true,
+    inline_flags: false,
+    atomic_groups: false,
+    shorthand_classes: true,
+    unicode_classes: true,
+    posix_classes: false,
+    letter_escapes: true,
+    bracket_escapes: true,
+    word_boundaries: true,
+    string_anchors: false,
+    end_anchor: None,
+    newline_end_anchor: false,
+    recursion: false,
+    basic_syntax: false,
+};
+
+const PCRE: Capabilities = Capabilities {
+    lookahead: true,
+    lookbehind: true,
+    backreferences: true,
+    named_groups: &["(?<name>...)", "(?P<name>...)", "(?'name'...)"],
+    possessive_quantifiers: true,
+    lazy_quantifiers: true,
+    non_capturing_groups: true,
+    inline_flags: true,
+    atomic_groups: true,
+    shorthand_classes: true,
+    unicode_classes: true,
+    posix_classes: true,
+    letter_escapes: true,
+    bracket_escapes: true,
+    word_boundaries: true,
+    string_anchors: true,
+    end_anchor: Some(r"\z"),
+    newline_end_anchor: true,
+    recursion: true,
+    basic_syntax: false,
+};
+
+const POSIX: Capabilities = Capabilities {
+    lookahead: false,
+    lookbehind: false,
+    backreferences: false,
+    named_groups: &[],
+    possessive_quantifiers: false,
+    lazy_quantifiers: false,
+    non_capturing_groups: false,
+    inline_flags: false,
+    atomic_groups: false,
+    shorthand_classes: false,
+    unicode_classes: false,
+    posix_classes: true,
+    letter_escapes: false,
+    bracket_escapes: false,
+    word_boundaries: false,
+    string_anchors: false,
+    end_anchor: None,
+    newline_end_anchor: false,
+    recursion: false,
+    basic_syntax: false,
+};
+
+const PYTHON: Capabilities = Capabilities {
+    lookahead: true,
+    lookbehind: true,
+    backreferences: true,
+    named_groups: &["(?P<name>...)"],
+    possessive_quantifiers: true,
+    lazy_quantifiers: true,
+    non_capturing_groups: true,
+    inline_flags: true,
+    atomic_groups: true,
+    shorthand_classes: true,
+    unicode_classes: false,
+    posix_classes: false,
+    letter_escapes: true,
+    bracket_escapes: true,
+    word_boundaries: true,
+    string_anchors: true,
+    end_anchor: Some(r"\Z"),
+    newline_end_anchor: false,
+    recursion: false,
+    basic_syntax: false,
+};
+
+const GO: Capabilities = Capabilities {
+    lookahead: false,
+    lookbehind: false,
+    backreferences: false,
+    named_groups: &["(?P<name>...)", "(?<name>...)"],
+    possessive_quantifiers: false,
+    lazy_quantifiers: true,
+    non_capturing_groups: true,
+    inline_flags: true,
+    atomic_groups: false,
+    shorthand_classes: true,
+    unicode_classes: true,
+    posix_classes: true,
+    letter_escapes: true,
+    bracket_escapes: true,
+    word_boundaries: true,
+    string_anchors: true,
+    end_anchor: Some(r"\z"),
+    newline_end_anchor: false,
+    recursion: false,
+    basic_syntax: false,
+};
+
+const JAVA: Capabilities = Capabilities {
+    lookahead: true,
+    lookbehind: true,
+    backreferences: true,
+    named_groups: &["(?<name>...)"],
+    possessive_quantifiers: true,
+    lazy_quantifiers: true,
+    non_capturing_groups: true,
+    inline_flags: true,
+    atomic_groups: true,
+    shorthand_classes: true,
+    unicode_classes: true,
+    posix_classes: false,
+    letter_escapes: true,
+    bracket_escapes: true,
+    word_boundaries: true,
+    string_anchors: true,
+    end_anchor: Some(r"\z"),
+    newline_end_anchor: true,
+    recursion: false,
+    basic_syntax: false,
+};
+
+const DOTNET: Capabilities = Capabilities {
+    lookahead: true,
+    lookbehind: true,
+    backreferences: true,
+    named_groups: &["(?<name>...)", "(?'name'...)"],
+    possessive_quantifiers: false,
+    lazy_quantifiers: true,
+    non_capturing_groups: true,
+    inline_flags: true,
+    atomic_groups: true,
+    shorthand_classes: true,
+    unicode_classes: true,
+    posix_classes: false,
+    letter_escapes: true,
+    bracket_escapes: true,
+    word_boundaries: true,
+    string_anchors: true,
+    end_anchor: Some(r"\z"),
+    newline_end_anchor: true,
+    recursion: false,
+    basic_syntax: false,
+};
+
+const BRE: Capabilities = Capabilities {
+    lookahead: false,
+    lookbehind: false,
+    backreferences: true,
+    named_groups: &[],
+    possessive_quantifiers: false,
+    lazy_quantifiers: false,
+    non_capturing_groups: false,
+    inline_flags: false,
+    atomic_groups: false,
+    shorthand_classes: false,
+    unicode_classes: false,
+    posix_classes: true,
+    letter_escapes: false,
+    bracket_escapes: false,
+    word_boundaries: false,
+    string_anchors: false,
+    end_anchor: None,
+    newline_end_anchor: false,
+    recursion: false,
+    basic_syntax: true,
+};
+
+impl Flavor {
+    /// Name used on the command line and in saved sessions.
+    pub fn name(self) -> &'static str {
+        match self {
+            Flavor::Rust => "rust",
+            Flavor::Js => "js",
+            Flavor::Pcre => "pcre",
+            Flavor::Posix => "posix",
+            Flavor::Python => "python",
+            Flavor::Go => "go",
+            Flavor::Java => "java",
+            Flavor::Dotnet => "dotnet",
+            Flavor::Bre => "bre",
+        }
+    }
+
+    /// Name used in prompts and messages.
+    pub fn label(self) -> &'static str {
+        match self {
+            Flavor::Rust => "Rust regex crate",
+            Flavor::Js => "JavaScript (ECMAScript)",
+            Flavor::Pcre => "PCRE",
+            Flavor::Posix => "POSIX ERE",
+            Flavor::Python => "Python re",
+            Flavor::Go => "Go regexp (RE2)",
+            Flavor::Java => "Java java.util.regex",
+            Flavor::Dotnet => ".NET System.Text.RegularExpressions",
+            Flavor::Bre => "POSIX BRE",
+        }
+    }
+
+    pub fn capabilities(self) -> &'static Capabilities {
+        match self {
+            Flavor::Rust => &RUST,
+            Flavor::Js => &JS,
+            Flavor::Pcre => &PCRE,
+            Flavor::Posix => &POSIX,
+            Flavor::Python => &PYTHON,
+            Flavor::Go => &GO,
+            Flavor::Java => &JAVA,
+            Flavor::Dotnet => &DOTNET,
+            Flavor::Bre => &BRE,
+        }
+    }
+
+    /// How the flavor reads syntax that looks the same elsewhere but is
+    /// not, for the model to keep in mind when writing or explaining.
+    pub fn syntax_notes(self) -> &'static [&'static str] {
+        match self {
+            Flavor::Rust => &[
+                "\\d, \\w, \\s and \\b are Unicode-aware unless (?-u) is set.",
+                "$ matches only at the very end of the input unless (?m) is set.",
+            ],
+            Flavor::Js => &[
+                "\\d, \\w and \\b are ASCII-only; \\p{..} needs the u or v flag.",
+                "Flags such as i, m and s are set on the RegExp, not inside the pattern.",
+            ],
+            Flavor::Pcre => &[
+                "$ and \\Z also match before a final newline; \\z matches only at the very end.",
+                "\\d, \\w and \\b are ASCII-only unless (*UCP) or the UCP option is set.",
+            ],
+            Flavor::Posix => &[
+                "Matching is leftmost-longest: among matches at the same position the longest wins, whatever the order of alternatives.",
+                "Inside a bracket expression a backslash is an ordinary character.",
+            ],
+            Flavor::Python => &[
+                "\\Z matches only at the very end of the string, like \\z elsewhere; \\z itself needs Python 3.14.",
+                "Lookbehind must be fixed-width. Possessive quantifiers and atomic groups need Python 3.11.",
+                "Named backreferences are written (?P=name).",
+            ],
+            Flavor::Go => &[
+                "\\d, \\w, \\s and \\b are ASCII-only.",
+                "Matching runs in linear time, which is why lookaround and backreferences are missing.",
+            ],
+            Flavor::Java => &[
+                "POSIX classes are written \\p{Alpha}, \\p{Digit} and so on, not [[:alpha:]].",
+                "Lookbehind must have a bounded length. In Java source every backslash is doubled; write the pattern itself, not the string literal.",
+            ],
+            Flavor::Dotnet => &[
+                "Lookbehind may be any length. Balancing groups (?<open-close>...) take the place of recursion.",
+                "\\d matches any Unicode decimal digit unless RegexOptions.ECMAScript is set.",
+            ],
+            Flavor::Bre => &[
+                "Groups are written \\(...\\) and intervals \\{m,n\\}; unescaped (, ), {, }, +, ? and | are ordinary characters.",
+                "There is no alternation and no one-or-more quantifier: write x\\{1,\\} for x+ and x\\{0,1\\} for x?.",
+                "* is an ordinary character at the start of the pattern or of a group. Backreferences \\1 to \\9 are supported.",
+            ],
+        }
+    }
+
+    /// Prompt lines telling the model what the flavor can and cannot do.
+    pub fn guidance(self) -> String {
+        let caps = self.capabilities();
+        let features = [
+            ("lookahead", caps.lookahead),
+            ("lookbehind", caps.lookbehind),
+            ("backreferences", caps.backreferences),
+            ("possessive quantifiers", caps.possessive_quantifiers),
+            ("lazy quantifiers", caps.lazy_quantifiers),
+            ("non-capturing groups (?:...)", caps.non_capturing_groups),
+            ("inline flags like (?i)", caps.inline_flags),
+            ("atomic groups (?>...)", caps.atomic_groups),
+            ("Unicode property classes like \\p{L}", caps.unicode_classes),
+            ("POSIX classes like [[:alpha:]]", caps.posix_classes),
+            ("word boundaries \\b", caps.word_boundaries),
+            ("the \\A anchor", caps.string_anchors),
+            ("the \\z anchor", caps.end_anchor == Some(r"\z")),
+            (
+                "\\Z matching before a final newline",
+                caps.newline_end_anchor,
+            ),
+            ("recursion", caps.recursion),
+        ];
+        let list = |supported: bool| {
+            features
+                .iter()
+                .filter(|(_, has)| *has == supported)
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut lines = Vec::new();
+        let supported = list(true);
+        if !supported.is_empty() {
+            lines.push(format!("{} supports: {}.", self.label(), supported));
+        }
+        let unsupported = list(false);
+        if !unsupported.is_empty() {
+            lines.push(format!(
+                "{} does NOT support: {}. Use only supported features.",
+                self.label(),
+                unsupported
+            ));
+        }
+        lines.push(match caps.named_groups {
+            [] => "Groups cannot be named; use plain numbered groups.".to_string(),
+            [preferred, ..] => format!("Write named groups as {}.", preferred),
+        });
+        lines.extend(self.syntax_notes().iter().map(|note| note.to_string()));
+        if !caps.shorthand_classes {
+            lines.push(
+                "There are no \\d, \\w or \\s shortcuts. Use bracket expressions like [0-9], [[:alnum:]_] and [[:space:]]."
+                    .to_string(),
+            );
+        }
+        lines.join("\n")
+    }
+}
+
+impl fmt::Display for Flavor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip_through_the_cli() {
+        for flavor in Flavor::value_variants() {
+            assert_eq!(Flavor::from_str(flavor.name(), false), Ok(*flavor));
+        }
+    }
+
+    #[test]
+    fn serializes_as_cli_name() {
+        assert_eq!(serde_json::to_string(&Flavor::Js).unwrap(), r#""js""#);
+        let old: Flavor = serde_json::from_str(r#""javascript""#).unwrap();
+        assert_eq!(old, Flavor::Js);
+    }
+
+    #[test]
+    fn rust_guidance_rules_out_lookaround() {
+        let guidance = Flavor::Rust.guidance();
+        assert!(guidance.contains("does NOT support: lookahead, lookbehind, backreferences"));
+        assert!(guidance.contains("(?<name>...)"));
+    }
+
+    #[test]
+    fn js_guidance_is_emitted() {
+        let guidance = Flavor::Js.guidance();
+        assert!(guidance.contains("JavaScript"));
+        assert!(guidance.contains("supports: lookahead, lookbehind"));
+        assert!(guidance.contains(
+            "the \\z anchor, \\Z matching before a final newline, recursion. Use only supported"
+        ));
+    }
+
+    #[test]
+    fn posix_guidance_spells_out_classes() {
+        let guidance = Flavor::Posix.guidance();
+        assert!(guidance.contains("POSIX ERE supports: POSIX classes like [[:alpha:]]."));
+        assert!(guidance.contains("[0-9]"));
+        assert!(guidance.contains("cannot be named"));
+    }
+
+    #[test]
+    fn go_also_answers_to_re2() {
+        assert_eq!(Flavor::from_str("re2", false), Ok(Flavor::Go));
+        assert_eq!(Flavor::Go.name(), "go");
+    }
+
+    #[test]
+    fn bre_guidance_describes_basic_syntax() {
+        let guidance = Flavor::Bre.guidance();
+        assert!(guidance.contains("POSIX BRE supports: backreferences, POSIX classes"));
+        assert!(guidance.contains("unescaped (, ), {, }, +, ? and | are ordinary characters"));
+        assert!(guidance.contains("[[:alnum:]_]"));
+    }
+
+    #[test]
+    fn pcre_supports_everything_listed() {
+        let guidance = Flavor::Pcre.guidance();
+        assert!(!guidance.contains("does NOT support"));
+        assert!(guidance.contains("recursion"));
+        assert_eq!(Flavor::Pcre.capabilities().named_groups.len(), 3);
+    }
+}
@@ -0,0 +1,544 @@
+//! Offline pattern synthesis from example strings, in the spirit of grex.
+//!
+//! Candidates are tried from most to least general; the first one that
+//! matches every positive example and rejects every negative one wins.
+//! Exact alternation of the examples always qualifies, so inference only
+//! fails when the same string is both a positive and a negative example, or
+//! for POSIX BRE, which has no alternation. BRE patterns are written in
+//! extended syntax and transliterated.
+
+use crate::commands::generate::GenerateResponse;
+use crate::engine::Engine;
+use crate::error::{Error, Result};
+use crate::flavor::{Capabilities, Flavor};
+use crate::syntax::extended_to_basic;
+
+/// Character class of a run of characters within an example.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Digit,
+    Alpha,
+    Space,
+    Literal(char),
+}
+
+/// A maximal run of characters sharing one class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Run {
+    class: Class,
+    len: usize,
+    lower: bool,
+    upper: bool,
+}
+
+fn classify(c: char) -> Class {
+    if c.is_ascii_digit() {
+        Class::Digit
+    } else if c.is_ascii_alphabetic() {
+        Class::Alpha
+    } else if c == ' ' || c == '\t' {
+        Class::Space
+    } else {
+        Class::Literal(c)
+    }
+}
+
+fn runs(s: &str) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for c in s.chars() {
+        let class = classify(c);
+        match runs.last_mut() {
+            Some(run) if run.class == class => run.len += 1,
+            _ => runs.push(Run {
+                class,
+                len: 1,
+                lower: false,
+                upper: false,
+            }),
+        }
+        let run = runs.last_mut().unwrap();
+        run.lower |= c.is_ascii_lowercase();
+        run.upper |= c.is_ascii_uppercase();
+    }
+    runs
+}
+
+fn quantifier(min: usize, max: usize) -> String {
+    match (min, max) {
+        (1, 1) => String::new(),
+        (min, max) if min == max => format!("{{{}}}", min),
+        (min, max) => format!("{{{},{}}}", min, max),
+    }
+}
+
+/// Whether `s` needs a group before a quantifier can apply to all of it.
+/// Single characters, escapes and bracket expressions are atoms already.
+fn needs_group(s: &str) -> bool {
+    let len = s.chars().count();
+    let atom =
+        len == 1 || (s.starts_with('\\') && len == 2) || (s.starts_with('[') && s.ends_with(']'));
+    !atom
+}
+
+struct Writer {
+    caps: &'static Capabilities,
+}
+
+impl Writer {
+    /// Escape characters that are special in any supported flavor, and `/`
+    /// for JavaScript literals where the flavor allows escaping it.
+    fn escape(&self, s: &str) -> String {
+        let mut out = String::with_capacity(s.len());
+        for c in s.chars() {
+            if "\\.+*?()|[]{}^$".contains(c) || (c == '/' && self.caps.letter_escapes) {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out
+    }
+
+    fn group(&self, inner: &str) -> String {
+        if self.caps.non_capturing_groups {
+            format!("(?:{})", inner)
+        } else {
+            format!("({})", inner)
+        }
+    }
+
+    fn repeat(&self, unit: &str, min: usize, max: usize) -> String {
+        let q = quantifier(min, max);
+        if q.is_empty() || !needs_group(unit) {
+            format!("{}{}", unit, q)
+        } else {
+            format!("{}{}", self.group(unit), q)
+        }
+    }
+
+    fn class(&self, class: Class, lower: bool, upper: bool) -> String {
+        match class {
+            Class::Digit if !self.caps.shorthand_classes => "[0-9]".to_string(),
+            Class::Digit => r"\d".to_string(),
+            Class::Space if !self.caps.shorthand_classes => "[[:blank:]]".to_string(),
+            Class::Space => r"\s".to_string(),
+            Class::Alpha => match (lower, upper) {
+                (true, false) => "[a-z]".to_string(),
+                (false, true) => "[A-Z]".to_string(),
+                _ => "[a-zA-Z]".to_string(),
+            },
+            Class::Literal(c) => self.escape(&c.to_string()),
+        }
+    }
+
+    fn anchored(&self, body: &str) -> String {
+        format!("^{}$", body)
+    }
+}
+
+fn common_prefix<'a>(strings: &[&'a str]) -> &'a str {
+    let first = strings[0];
+    let mut end = first.len();
+    for s in &strings[1..] {
+        end = first
+            .char_indices()
+            .zip(s.chars())
+            .take_while(|((_, a), b)| a == b)
+            .map(|((i, a), _)| i + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+            .min(end);
+    }
+    &first[..end]
+}
+
+fn common_suffix<'a>(strings: &[&'a str]) -> &'a str {
+    let first = strings[0];
+    let mut start = 0;
+    for s in &strings[1..] {
+        let matched: usize = first
+            .chars()
+            .rev()
+            .zip(s.chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        start = start.max(first.len() - matched);
+    }
+    &first[start..]
+}
+
+/// Smallest unit `u` and count `k` such that `s == u.repeat(k)`.
+fn repetition(s: &str) -> (&str, usize) {
+    let chars: Vec<(usize, char)> = s.char_indices().collect();
+    for unit_chars in 1..=chars.len() / 2 {
+        if !chars.len().is_multiple_of(unit_chars) {
+            continue;
+        }
+        let unit_end = chars[unit_chars].0;
+        let unit = &s[..unit_end];
+        if unit.repeat(chars.len() / unit_chars) == s {
+            return (unit, chars.len() / unit_chars);
+        }
+    }
+    (s, 1)
+}
+
+/// Literal written as a repeated unit when that is shorter, e.g. "aaaaa" -> "a{5}".
+fn compress_literal(writer: &Writer, s: &str) -> String {
+    let literal = writer.escape(s);
+    match repetition(s) {
+        (unit, count) if count > 1 => {
+            let repeated = writer.repeat(&writer.escape(unit), count, count);
+            if repeated.len() < literal.len() {
+                repeated
+            } else {
+                literal
+            }
+        }
+        _ => literal,
+    }
+}
+
+/// Every example is one unit repeated a varying number of times.
+fn repeated_unit(writer: &Writer, examples: &[&str]) -> Option<String> {
+    let reps: Vec<(&str, usize)> = examples.iter().map(|s| repetition(s)).collect();
+    let unit = reps[0].0;
+    if unit.is_empty() || reps.iter().any(|(u, _)| *u != unit) {
+        return None;
+    }
+    let min = reps.iter().map(|(_, k)| *k).min()?;
+    let max = reps.iter().map(|(_, k)| *k).max()?;
+    if max < 2 {
+        return None;
+    }
+    Some(writer.anchored(&writer.repeat(&writer.escape(unit), min, max)))
+}
+
+/// Every example has the same sequence of character classes; generalize
+/// each run to its class with the observed length range.
+fn class_shape(writer: &Writer, examples: &[&str]) -> Option<String> {
+    let all_runs: Vec<Vec<Run>> = examples.iter().map(|s| runs(s)).collect();
+    let shape = &all_runs[0];
+    if shape.is_empty()
+        || all_runs
+            .iter()
+            .any(|r| r.len() != shape.len() || r.iter().zip(shape).any(|(a, b)| a.class != b.class))
+    {
+        return None;
+    }
+
+    let mut body = String::new();
+    for i in 0..shape.len() {
+        let column: Vec<&Run> = all_runs.iter().map(|r| &r[i]).collect();
+        let min = column.iter().map(|r| r.len).min()?;
+        let max = column.iter().map(|r| r.len).max()?;
+        let lower = column.iter().any(|r| r.lower);
+        let upper = column.iter().any(|r| r.upper);
+        let class = writer.class(shape[i].class, lower, upper);
+        body.push_str(&writer.repeat(&class, min, max));
+    }
+    Some(writer.anchored(&body))
+}
+
+/// Exact alternation of the examples with common prefix and suffix factored out.
+fn alternation(writer: &Writer, examples: &[&str]) -> String {
+    if examples.len() == 1 {
+        return writer.anchored(&compress_literal(writer, examples[0]));
+    }
+
+    let prefix = common_prefix(examples);
+    let rests: Vec<&str> = examples.iter().map(|s| &s[prefix.len()..]).collect();
+    let suffix = common_suffix(&rests);
+    let middles: Vec<&str> = rests.iter().map(|s| &s[..s.len() - suffix.len()]).collect();
+
+    let optional = middles.iter().any(|m| m.is_empty());
+    let alternatives: Vec<String> = middles
+        .iter()
+        .filter(|m| !m.is_empty())
+        .map(|m| compress_literal(writer, m))
+        .collect();
+
+    let middle = match alternatives.as_slice() {
+        [] => String::new(),
+        [single] if !optional => single.clone(),
+        [single] if !needs_group(single) => format!("{}?", single),
+        _ => {
+            let group = writer.group(&alternatives.join("|"));
+            if optional {
+                format!("{}?", group)
+            } else {
+                group
+            }
+        }
+    };
+
+    writer.anchored(&format!(
+        "{}{}{}",
+        writer.escape(prefix),
+        middle,
+        writer.escape(suffix)
+    ))
+}
+
+fn accepts(pattern: &str, flavor: Flavor, positives: &[&str], negatives: &[String]) -> bool {
+    match Engine::compile(pattern, flavor) {
+        Ok(regex) => {
+            positives.iter().all(|p| regex.is_match(p))
+                && !negatives.iter().any(|n| regex.is_match(n))
+        }
+        Err(_) => false,
+    }
+}
+
+/// Build a pattern for `flavor` that matches every positive example and no
+/// negative one, without consulting a model.
+pub fn infer(
+    positives: &[String],
+    negatives: &[String],
+    flavor: Flavor,
+) -> Result<GenerateResponse> {
+    let mut examples: Vec<&str> = Vec::new();
+    for p in positives {
+        if !examples.contains(&p.as_str()) {
+            examples.push(p);
+        }
+    }
+    if examples.is_empty() {
+        return Err(Error::InvalidFlags(
+            "--infer needs at least one --match example".to_string(),
+        ));
+    }
+    if let Some(both) = examples.iter().find(|p| negatives.iter().any(|n| n == *p)) {
+        return Err(Error::InvalidFlags(format!(
+            "{:?} is given as both a --match and a --no-match example",
+            both
+        )));
+    }
+
+    let basic = flavor.capabilities().basic_syntax;
+    let writer = Writer {
+        caps: if basic {
+            Flavor::Posix.capabilities()
+        } else {
+            flavor.capabilities()
+        },
+    };
+    let candidates = [
+        (
+            "repetition of a common unit",
+            repeated_unit(&writer, &examples),
+        ),
+        (
+            "generalized character classes",
+            class_shape(&writer, &examples),
+        ),
+        (
+            "alternation of the examples",
+            Some(alternation(&writer, &examples)),
+        ),
+    ];
+
+    let (strategy, pattern) = candidates
+        .into_iter()
+        .find_map(|(strategy, pattern)| {
+            pattern
+                .map(|p| if basic { extended_to_basic(&p) } else { p })
+                .filter(|p| accepts(p, flavor, &examples, negatives))
+                .map(|p| (strategy, p))
+        })
+        .ok_or_else(|| {
+            Error::InvalidFlags("could not infer a pattern from the examples".to_string())
+        })?;
+
+    Ok(GenerateResponse {
+        pattern,
+        matches: examples.iter().map(|s| s.to_string()).collect(),
+        non_matches: negatives.to_vec(),
+        explanation: format!(
+            "Inferred locally from {} example(s) by {}.",
+            examples.len(),
+            strategy
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn infer_rust(positives: &[&str], negatives: &[&str]) -> String {
+        infer(&strings(positives), &strings(negatives), Flavor::Rust)
+            .unwrap()
+            .pattern
+    }
+
+    #[test]
+    fn dates_generalize_to_digit_classes() {
+        assert_eq!(
+            infer_rust(&["2024-01-15", "1999-12-31"], &[]),
+            r"^\d{4}-\d{2}-\d{2}$"
+        );
+    }
+
+    #[test]
+    fn varying_run_lengths_use_ranges() {
+        assert_eq!(
+            infer_rust(&["a1", "bc22", "def333"], &[]),
+            r"^[a-z]{1,3}\d{1,3}$"
+        );
+    }
+
+    #[test]
+    fn mixed_case_letters() {
+        assert_eq!(infer_rust(&["Ab", "cD"], &[]), "^[a-zA-Z]{2}$");
+    }
+
+    #[test]
+    fn punctuation_stays_literal_and_escaped() {
+        assert_eq!(
+            infer_rust(&["a.b@x.io", "cd.ef@yz.com"], &[]),
+            r"^[a-z]{1,2}\.[a-z]{1,2}@[a-z]{1,2}\.[a-z]{2,3}$"
+        );
+    }
+
+    #[test]
+    fn repetition_detected_across_examples() {
+        assert_eq!(infer_rust(&["ab", "abab", "ababab"], &[]), "^(?:ab){1,3}$");
+    }
+
+    #[test]
+    fn single_char_repetition_needs_no_group() {
+        assert_eq!(infer_rust(&["xx", "xxxx"], &[]), "^x{2,4}$");
+    }
+
+    #[test]
+    fn negatives_force_alternation() {
+        let pattern = infer_rust(&["cat", "dog"], &["cow"]);
+        assert_eq!(pattern, "^(?:cat|dog)$");
+    }
+
+    #[test]
+    fn alternation_factors_prefix_and_suffix() {
+        let pattern = infer_rust(&["foo-1.txt", "foo-22.txt", "foo-x.txt"], &["foo-.txt"]);
+        assert_eq!(pattern, r"^foo-(?:1|22|x)\.txt$");
+    }
+
+    #[test]
+    fn alternation_with_optional_middle() {
+        let pattern = infer_rust(&["color", "colour"], &["colur"]);
+        assert_eq!(pattern, "^colou?r$");
+    }
+
+    #[test]
+    fn long_literal_runs_compressed() {
+        let pattern = infer_rust(&["aaaaaa", "b"], &["aa"]);
+        assert_eq!(pattern, "^(?:a{6}|b)$");
+    }
+
+    #[test]
+    fn single_example() {
+        assert_eq!(infer_rust(&["v1.2"], &[]), r"^[a-z]\d\.\d$");
+    }
+
+    #[test]
+    fn posix_avoids_shorthand_and_noncapturing_groups() {
+        let pattern = infer(&strings(&["ab", "abab"]), &[], Flavor::Posix)
+            .unwrap()
+            .pattern;
+        assert_eq!(pattern, "^(ab){1,2}$");
+        let pattern = infer(&strings(&["12 ab"]), &[], Flavor::Posix)
+            .unwrap()
+            .pattern;
+        assert_eq!(pattern, "^[0-9]{2}[[:blank:]][a-z]{2}$");
+    }
+
+    #[test]
+    fn posix_leaves_slash_unescaped() {
+        let pattern = infer(&strings(&["a/b"]), &[], Flavor::Posix)
+            .unwrap()
+            .pattern;
+        assert_eq!(pattern, "^[a-z]/[a-z]$");
+        assert_eq!(infer_rust(&["a/b"], &[]), r"^[a-z]\/[a-z]$");
+    }
+
+    #[test]
+    fn bre_written_in_basic_syntax() {
+        let pattern = infer(&strings(&["ab", "abab"]), &[], Flavor::Bre)
+            .unwrap()
+            .pattern;
+        assert_eq!(pattern, r"^\(ab\)\{1,2\}$");
+        let pattern = infer(&strings(&["a+b"]), &strings(&["ab"]), Flavor::Bre)
+            .unwrap()
+            .pattern;
+        assert_eq!(pattern, "^[a-z]+[a-z]$");
+        assert!(infer(&strings(&["cat", "dog"]), &strings(&["cow"]), Flavor::Bre).is_err());
+    }
+
+    #[test]
+    fn result_rejects_all_negatives() {
+        let negatives = strings(&["2024-1-15", "24-01-15"]);
+        let resp = infer(
+            &strings(&["2024-01-15", "1999-12-31"]),
+            &negatives,
+            Flavor::Rust,
+        )
+        .unwrap();
+        let regex = Regex::new(&resp.pattern).unwrap();
+        assert!(negatives.iter().all(|n| !regex.is_match(n)));
+        assert_eq!(resp.non_matches, negatives);
+    }
+
+    #[test]
+    fn duplicates_removed_from_matches() {
+        let resp = infer(&strings(&["a", "a", "b"]), &[], Flavor::Rust).unwrap();
+        assert_eq!(resp.matches, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn explanation_names_strategy() {
+        let resp = infer(&strings(&["123"]), &[], Flavor::Rust).unwrap();
+        assert!(resp.explanation.contains("1 example"));
+        assert!(resp.explanation.contains("character classes"));
+    }
+
+    #[test]
+    fn metacharacters_escaped() {
+        let pattern = infer_rust(&["a+b", "(c)"], &["ab"]);
+        let regex = Regex::new(&pattern).unwrap();
+        assert!(regex.is_match("a+b"));
+        assert!(regex.is_match("(c)"));
+        assert!(!regex.is_match("ab"));
+    }
+
+    #[test]
+    fn no_positives_is_error() {
+        assert!(infer(&[], &strings(&["x"]), Flavor::Rust).is_err());
+    }
+
+    #[test]
+    fn contradictory_examples_are_error() {
+        let err = infer(&strings(&["x"]), &strings(&["x"]), Flavor::Rust).unwrap_err();
+        assert!(err.to_string().contains("both"));
+    }
+
+    #[test]
+    fn common_prefix_and_suffix_helpers() {
+        assert_eq!(common_prefix(&["abcd", "abef"]), "ab");
+        assert_eq!(common_prefix(&["abc", "xyz"]), "");
+        assert_eq!(common_suffix(&["xing", "ying"]), "ing");
+        assert_eq!(common_suffix(&["ab", "b"]), "b");
+    }
+
+    #[test]
+    fn repetition_helper() {
+        assert_eq!(repetition("abcabc"), ("abc", 2));
+        assert_eq!(repetition("aaa"), ("a", 3));
+        assert_eq!(repetition("abca"), ("abca", 1));
+        assert_eq!(repetition(""), ("", 1));
+    }
+}
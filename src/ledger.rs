@@ -0,0 +1,331 @@
+use crate::backend::{Backend, Metadata, Reply, Usage};
+use crate::config::xdg_dir;
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What a single rgx run spent, one JSON object per ledger line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Unix time the run finished
+    pub time: u64,
+    pub backend: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub usage: Usage,
+}
+
+/// Totals for one calendar month (UTC).
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MonthSummary {
+    /// `YYYY-MM`
+    pub month: String,
+    pub runs: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost of the runs whose backend reported one
+    pub cost_usd: f64,
+    /// Runs without a reported cost, left out of `cost_usd`
+    pub unpriced: usize,
+    pub duration_ms: u64,
+}
+
+/// Append-only JSON Lines file of every run's usage.
+pub struct Ledger {
+    path: PathBuf,
+}
+
+impl Ledger {
+    pub fn new(path: PathBuf) -> Self {
+        Ledger { path }
+    }
+
+    /// `$XDG_DATA_HOME/rgx/ledger.jsonl`, or under `~/.local/share`.
+    pub fn default_path() -> Option<PathBuf> {
+        xdg_dir("XDG_DATA_HOME", ".local/share").map(|dir| dir.join("ledger.jsonl"))
+    }
+
+    /// Append the usage in `metadata`. Runs without usage, such as cache
+    /// hits, cost nothing and are not recorded.
+    pub fn record(&self, metadata: &Metadata) -> Result<()> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.record_at(metadata, time)
+    }
+
+    fn record_at(&self, metadata: &Metadata, time: u64) -> Result<()> {
+        let Some(usage) = &metadata.usage else {
+            return Ok(());
+        };
+        let entry = Entry {
+            time,
+            backend: metadata.backend.clone(),
+            model: metadata.model.clone(),
+            usage: usage.clone(),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+
+    /// Every entry in the ledger, skipping lines that do not parse.
+    pub fn entries(&self) -> Result<Vec<Entry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(fs::read_to_string(&self.path)?
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Totals per month, oldest first.
+    pub fn monthly(&self) -> Result<Vec<MonthSummary>> {
+        let mut months: Vec<MonthSummary> = Vec::new();
+        let mut entries = self.entries()?;
+        entries.sort_by_key(|entry| entry.time);
+
+        for entry in entries {
+            let month = month(entry.time);
+            if months.last().is_none_or(|last| last.month != month) {
+                months.push(MonthSummary {
+                    month,
+                    runs: 0,
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    cost_usd: 0.0,
+                    unpriced: 0,
+                    duration_ms: 0,
+                });
+            }
+            let summary = months.last_mut().expect("pushed above");
+            summary.runs += 1;
+            summary.input_tokens += entry.usage.input_tokens;
+            summary.output_tokens += entry.usage.output_tokens;
+            match entry.usage.cost_usd {
+                Some(cost) => summary.cost_usd += cost,
+                None => summary.unpriced += 1,
+            }
+            summary.duration_ms += entry.usage.duration_ms;
+        }
+        Ok(months)
+    }
+}
+
+/// Backend wrapper that totals the usage of every reply, so a run that fails
+/// after spending tokens can still be recorded.
+pub struct Metered<'a, B: ?Sized> {
+    inner: &'a B,
+    spent: RefCell<Option<Usage>>,
+}
+
+impl<'a, B: Backend + ?Sized> Metered<'a, B> {
+    pub fn new(inner: &'a B) -> Self {
+        Metered {
+            inner,
+            spent: RefCell::new(None),
+        }
+    }
+
+    /// The backend's metadata with the usage of every reply so far, or
+    /// `None` when nothing was spent.
+    pub fn spent(&self) -> Option<Metadata> {
+        let usage = self.spent.borrow().clone()?;
+        Some(Metadata {
+            usage: Some(usage),
+            ..self.inner.metadata()
+        })
+    }
+
+    fn count(&self, reply: Result<Reply>) -> Result<Reply> {
+        if let Ok(Reply {
+            metadata: Metadata {
+                usage: Some(usage), ..
+            },
+            ..
+        }) = &reply
+        {
+            *self.spent.borrow_mut().get_or_insert_with(Usage::default) += usage;
+        }
+        reply
+    }
+}
+
+impl<B: Backend + ?Sized> Backend for Metered<'_, B> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn identity(&self) -> String {
+        self.inner.identity()
+    }
+
+    fn query(&self, prompt: &str) -> Result<Reply> {
+        self.count(self.inner.query(prompt))
+    }
+
+    fn resume(&self, session_id: &str, prompt: &str) -> Result<Reply> {
+        self.count(self.inner.resume(session_id, prompt))
+    }
+
+    fn query_structured(&self, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.count(self.inner.query_structured(prompt, schema))
+    }
+
+    fn resume_structured(&self, session_id: &str, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.count(self.inner.resume_structured(session_id, prompt, schema))
+    }
+
+    fn discard(&self, prompt: &str, schema: &Value) {
+        self.inner.discard(prompt, schema)
+    }
+
+    fn metadata(&self) -> Metadata {
+        self.inner.metadata()
+    }
+}
+
+/// `YYYY-MM` of a Unix time, in UTC.
+fn month(time: u64) -> String {
+    // Civil-from-days conversion from Howard Hinnant's date algorithms
+    let days = (time / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}", year, month)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_metadata(input: u64, cost: Option<f64>) -> Metadata {
+        Metadata {
+            backend: "claude".to_string(),
+            model: "haiku".to_string(),
+            usage: Some(Usage {
+                input_tokens: input,
+                output_tokens: 10,
+                cost_usd: cost,
+                duration_ms: 2000,
+            }),
+            ..Metadata::default()
+        }
+    }
+
+    #[test]
+    fn month_of_unix_time() {
+        assert_eq!(month(0), "1970-01");
+        assert_eq!(month(951_782_400), "2000-02"); // 2000-02-29
+        assert_eq!(month(1_790_812_799), "2026-09"); // 2026-09-30 23:59:59
+        assert_eq!(month(1_790_812_800), "2026-10");
+    }
+
+    #[test]
+    fn record_appends_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let ledger = Ledger::new(dir.path().join("data").join("ledger.jsonl"));
+        ledger
+            .record_at(&make_metadata(100, Some(0.01)), 1)
+            .unwrap();
+        ledger.record_at(&make_metadata(200, None), 2).unwrap();
+
+        let entries = ledger.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].usage.input_tokens, 100);
+        assert_eq!(entries[1].usage.cost_usd, None);
+        assert_eq!(entries[1].model, "haiku");
+    }
+
+    #[test]
+    fn runs_without_usage_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let ledger = Ledger::new(dir.path().join("ledger.jsonl"));
+        ledger.record(&Metadata::default()).unwrap();
+        assert!(ledger.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn monthly_totals() {
+        let dir = tempfile::tempdir().unwrap();
+        let ledger = Ledger::new(dir.path().join("ledger.jsonl"));
+        let september = 1_790_000_000;
+        let october = 1_791_000_000;
+        ledger
+            .record_at(&make_metadata(100, Some(0.25)), october)
+            .unwrap();
+        ledger
+            .record_at(&make_metadata(100, Some(0.5)), september)
+            .unwrap();
+        ledger
+            .record_at(&make_metadata(50, None), october + 60)
+            .unwrap();
+
+        let months = ledger.monthly().unwrap();
+        assert_eq!(months.len(), 2);
+        assert_eq!(months[0].month, "2026-09");
+        assert_eq!(months[0].runs, 1);
+        assert_eq!(months[1].month, "2026-10");
+        assert_eq!(months[1].runs, 2);
+        assert_eq!(months[1].input_tokens, 150);
+        assert_eq!(months[1].output_tokens, 20);
+        assert_eq!(months[1].cost_usd, 0.25);
+        assert_eq!(months[1].unpriced, 1);
+        assert_eq!(months[1].duration_ms, 4000);
+    }
+
+    #[test]
+    fn metered_totals_every_reply() {
+        use crate::backend::mock::MockBackend;
+        let usage = Usage {
+            input_tokens: 100,
+            output_tokens: 20,
+            cost_usd: Some(0.5),
+            duration_ms: 1000,
+        };
+        let backend = MockBackend::new(["one", "two"]).with_usage(usage);
+        let metered = Metered::new(&backend);
+        assert!(metered.spent().is_none());
+        metered.query("a").unwrap();
+        metered.query("b").unwrap();
+        assert!(metered.query("c").is_err());
+
+        let spent = metered.spent().unwrap();
+        assert_eq!(spent.backend, "mock");
+        let usage = spent.usage.unwrap();
+        assert_eq!(usage.input_tokens, 200);
+        assert_eq!(usage.cost_usd, Some(1.0));
+    }
+
+    #[test]
+    fn unreadable_lines_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.jsonl");
+        fs::write(&path, "garbage\n").unwrap();
+        let ledger = Ledger::new(path);
+        ledger.record_at(&make_metadata(1, None), 1).unwrap();
+        assert_eq!(ledger.entries().unwrap().len(), 1);
+    }
+}
@@ -1,4 +1,20 @@
-pub mod claude;
+pub mod backend;
+pub mod cache;
+pub mod check;
 pub mod commands;
+pub mod config;
+pub mod engine;
 pub mod error;
+pub mod extract;
+pub mod fixture;
+pub mod flavor;
+pub mod infer;
+pub mod ledger;
+pub mod log;
 pub mod output;
+pub mod process;
+pub mod rewrite;
+pub mod session;
+pub mod spinner;
+pub mod syntax;
+pub mod verify;
@@ -0,0 +1,24 @@
+use crate::config::env_var;
+use crossterm::style::Stylize;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DEBUG: AtomicBool = AtomicBool::new(false);
+
+/// Turn debug output on for the rest of the run, as `--debug` does.
+pub fn enable() {
+    DEBUG.store(true, Ordering::SeqCst);
+}
+
+/// Whether debug output is on: `--debug`, or `RGX_LOG` set to any
+/// non-empty value.
+pub fn enabled() -> bool {
+    DEBUG.load(Ordering::SeqCst) || env_var("RGX_LOG").is_some()
+}
+
+/// Print `message` to stderr when debug output is on.
+pub fn debug(message: impl Display) {
+    if enabled() {
+        eprintln!("{}: {}", "debug".dim(), message);
+    }
+}
@@ -1,43 +1,58 @@
-mod claude;
+mod backend;
+mod cache;
+mod check;
 mod commands;
+mod config;
+mod engine;
 mod error;
+mod extract;
+mod fixture;
+mod flavor;
+mod infer;
+mod ledger;
+mod log;
 mod output;
+mod process;
+mod rewrite;
+mod session;
+mod spinner;
+mod syntax;
+mod verify;
 
-use clap::{CommandFactory, Parser, ValueEnum};
+use backend::anthropic::Anthropic;
+use backend::claude::Claude;
+use backend::openai::OpenAi;
+use backend::{Backend, BackendKind, Metadata};
+use cache::{Cache, Cached};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::{generate, Shell};
+use commands::convert::ConvertCommand;
 use commands::explain::ExplainCommand;
-use commands::generate::GenerateCommand;
+use commands::generate::{GenerateCommand, Generation, DEFAULT_RETRIES};
 use commands::test::TestCommand;
 use commands::Command;
+use config::{env_var, Config};
 use crossterm::style::Stylize;
 use error::{Error, Result};
-use std::io;
-
-#[derive(ValueEnum, Clone, Debug, Default)]
-pub enum Flavor {
-    #[default]
-    Rust,
-    Js,
-    Pcre,
-    Posix,
-}
-
-impl Flavor {
-    fn as_str(&self) -> &'static str {
-        match self {
-            Flavor::Rust => "rust",
-            Flavor::Js => "javascript",
-            Flavor::Pcre => "pcre",
-            Flavor::Posix => "posix",
-        }
-    }
-}
+use fixture::{Recorder, Replay};
+use flavor::Flavor;
+use ledger::{Ledger, Metered};
+use rewrite::Conversion;
+use session::{Session, SessionStore};
+use spinner::Spinner;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "rgx")]
 #[command(about = "Natural language regex patterns powered by Claude")]
 #[command(version)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
     /// Pattern description (generate/test mode) or pattern to explain (explain mode)
     input: Option<String>,
 
@@ -46,68 +61,542 @@ struct Cli {
     explain: bool,
 
     /// Test mode: generate pattern and test against this input string
-    #[arg(short = 't', long = "test", value_name = "INPUT")]
+    #[arg(short = 't', long = "test", value_name = "INPUT", global = true)]
     test: Option<String>,
 
     /// Output raw JSON
-    #[arg(long = "raw")]
+    #[arg(long = "raw", global = true)]
     raw: bool,
 
-    /// Regex flavor (affects pattern generation)
-    #[arg(long = "flavor", value_enum, default_value_t = Flavor::Rust)]
+    /// Regex flavor: shapes prompts, explanations and highlighting, and is
+    /// what `check` checks against
+    #[arg(long = "flavor", value_enum, default_value_t = Flavor::Rust, global = true)]
     flavor: Flavor,
 
+    /// Model to use, e.g. haiku, sonnet, opus [env: RGX_MODEL]
+    #[arg(short = 'm', long = "model", global = true)]
+    model: Option<String>,
+
+    /// String the generated pattern must match (repeatable)
+    #[arg(long = "match", value_name = "STR", global = true)]
+    must_match: Vec<String>,
+
+    /// String the generated pattern must not match (repeatable)
+    #[arg(long = "no-match", value_name = "STR", global = true)]
+    must_not_match: Vec<String>,
+
+    /// File of strings that must match, one per line
+    #[arg(long = "match-file", value_name = "FILE", global = true)]
+    match_file: Option<PathBuf>,
+
+    /// File of strings that must not match, one per line
+    #[arg(long = "no-match-file", value_name = "FILE", global = true)]
+    no_match_file: Option<PathBuf>,
+
+    /// Infer a pattern locally from --match/--no-match examples, without a model
+    #[arg(long = "infer")]
+    infer: bool,
+
+    /// Times to re-prompt when a generated pattern fails its own examples
+    #[arg(long = "retries", value_name = "N", default_value_t = DEFAULT_RETRIES, global = true)]
+    retries: usize,
+
+    /// Backend that answers prompts [default: claude]
+    #[arg(long = "backend", value_enum, global = true)]
+    backend: Option<BackendKind>,
+
+    /// Don't read or write the response cache
+    #[arg(long = "no-cache", global = true)]
+    no_cache: bool,
+
+    /// Ignore cached responses but store the fresh ones
+    #[arg(long = "refresh", conflicts_with = "no_cache", global = true)]
+    refresh: bool,
+
+    /// Seconds a cached response stays valid [default: 604800]
+    #[arg(long = "cache-ttl", value_name = "SECS", global = true)]
+    cache_ttl: Option<u64>,
+
+    /// Print tokens, cost and time spent to stderr
+    #[arg(long = "stats", global = true)]
+    stats: bool,
+
+    /// Program to run for the claude backend [env: RGX_CLAUDE_BIN]
+    #[arg(long = "claude-bin", value_name = "PATH", global = true)]
+    claude_bin: Option<String>,
+
+    /// Seconds to wait for the backend before giving up [default: 120]
+    #[arg(long = "timeout", value_name = "SECS", global = true)]
+    timeout: Option<u64>,
+
+    /// Write every prompt and reply to fixture files in DIR
+    #[arg(long = "record", value_name = "DIR", global = true)]
+    record: Option<PathBuf>,
+
+    /// Answer from fixtures recorded with --record instead of a backend
+    #[arg(
+        long = "replay",
+        value_name = "DIR",
+        conflicts_with = "record",
+        global = true
+    )]
+    replay: Option<PathBuf>,
+
+    /// Print prompts, backend calls and raw replies to stderr [env: RGX_LOG]
+    #[arg(long = "debug", global = true)]
+    debug: bool,
+
+    /// Print the prompt that would be sent and exit without calling a backend
+    #[arg(long = "dry-run", global = true)]
+    dry_run: bool,
+
     /// Generate shell completions
     #[arg(long = "completions", value_name = "SHELL")]
     completions: Option<Shell>,
 }
 
+#[derive(Subcommand)]
+enum Commands {
+    /// Ask the model to adjust the last pattern generated in this directory
+    Refine {
+        /// What to change, e.g. "also allow plus-addressing"
+        instruction: String,
+    },
+    /// Show tokens and cost spent per month
+    Stats,
+    /// List constructs in a pattern that a flavor does not support; exits 1
+    /// if there are any
+    Check {
+        /// Pattern to check
+        pattern: String,
+    },
+    /// Translate a pattern to another flavor; exits 1 if it cannot be
+    /// translated
+    Convert {
+        /// Pattern to translate
+        pattern: String,
+        /// Flavor the pattern is written in
+        #[arg(long = "from", value_enum)]
+        from: Flavor,
+        /// Flavor to translate to
+        #[arg(long = "to", value_enum)]
+        to: Flavor,
+    },
+}
+
+/// Files rgx keeps between runs. Each is absent when no home directory can
+/// be found.
+#[derive(Default)]
+struct State {
+    sessions: Option<SessionStore>,
+    ledger: Option<Ledger>,
+}
+
+impl State {
+    fn load() -> Self {
+        State {
+            sessions: SessionStore::default_path().map(SessionStore::new),
+            ledger: Ledger::default_path().map(Ledger::new),
+        }
+    }
+}
+
+fn has_user_examples(cli: &Cli) -> bool {
+    !cli.must_match.is_empty()
+        || !cli.must_not_match.is_empty()
+        || cli.match_file.is_some()
+        || cli.no_match_file.is_some()
+}
+
 fn validate_flags(cli: &Cli) -> Result<()> {
     if cli.explain && cli.test.is_some() {
         return Err(Error::InvalidFlags(
             "Cannot combine -e (explain) and -t (test) flags".to_string(),
         ));
     }
+    if cli.explain && cli.infer {
+        return Err(Error::InvalidFlags(
+            "Cannot combine -e (explain) and --infer flags".to_string(),
+        ));
+    }
+    if cli.dry_run && cli.infer {
+        return Err(Error::InvalidFlags(
+            "Cannot combine --dry-run and --infer: inference sends no prompt".to_string(),
+        ));
+    }
+    if cli.explain && has_user_examples(cli) {
+        return Err(Error::InvalidFlags(
+            "Cannot combine -e (explain) with --match/--no-match examples".to_string(),
+        ));
+    }
     Ok(())
 }
 
-fn run(cli: Cli) -> Result<()> {
-    validate_flags(&cli)?;
+/// Pick the model: `--model`, then `env_model` from `RGX_MODEL`, then the
+/// config file, then the backend's own default.
+fn resolve_model(
+    cli: &Cli,
+    config: &Config,
+    kind: BackendKind,
+    env_model: Option<String>,
+) -> String {
+    cli.model
+        .clone()
+        .or(env_model)
+        .or_else(|| config.model_for(kind).map(str::to_string))
+        .unwrap_or_else(|| kind.default_model().to_string())
+}
+
+fn build_backend(cli: &Cli, config: &Config) -> Result<Box<dyn Backend>> {
+    if let Some(dir) = &cli.replay {
+        return Ok(Box::new(Replay::new(dir.clone())));
+    }
+    let kind = cli.backend.or(config.backend).unwrap_or_default();
+    let model = resolve_model(cli, config, kind, env_var("RGX_MODEL"));
+    let timeout = cli
+        .timeout
+        .or(config.timeout)
+        .map(Duration::from_secs)
+        .unwrap_or(backend::DEFAULT_TIMEOUT);
+    let backend: Box<dyn Backend> = match kind {
+        BackendKind::Claude => {
+            let claude = Claude::from_config(&config.claude, &model, env_var("RGX_CLAUDE_BIN"));
+            let claude = match &cli.claude_bin {
+                Some(bin) => claude.with_bin(bin),
+                None => claude,
+            };
+            Box::new(claude.with_timeout(timeout))
+        }
+        BackendKind::Anthropic => {
+            Box::new(Anthropic::from_config(&config.anthropic, &model)?.with_timeout(timeout))
+        }
+        BackendKind::Openai => {
+            Box::new(OpenAi::from_config(&config.openai, &model).with_timeout(timeout))
+        }
+    };
+    // Inside the cache, so instant cache hits never start a spinner. Debug
+    // output would tear through the spinner line, so it gets none.
+    let backend: Box<dyn Backend> = if io::stderr().is_terminal() && !log::enabled() {
+        Box::new(Spinner::new(backend))
+    } else {
+        backend
+    };
+
+    let backend: Box<dyn Backend> = match Cache::default_dir() {
+        Some(dir) if !cli.no_cache => {
+            let ttl = cli
+                .cache_ttl
+                .or(config.cache_ttl)
+                .map(Duration::from_secs)
+                .unwrap_or(cache::DEFAULT_TTL);
+            Box::new(Cached::new(backend, Cache::new(dir, ttl)).with_refresh(cli.refresh))
+        }
+        _ => backend,
+    };
+
+    // Outside the cache, so a recording holds every reply the run used
+    match &cli.record {
+        Some(dir) => Ok(Box::new(Recorder::new(backend, dir.clone()))),
+        None => Ok(backend),
+    }
+}
+
+/// Read one example per line, skipping blank lines.
+fn read_examples(path: &Path) -> Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Examples from `--match`/`--no-match` and their file forms.
+fn user_examples(cli: &Cli) -> Result<(Vec<String>, Vec<String>)> {
+    let mut must_match = cli.must_match.clone();
+    if let Some(path) = &cli.match_file {
+        must_match.extend(read_examples(path)?);
+    }
+    let mut must_not_match = cli.must_not_match.clone();
+    if let Some(path) = &cli.no_match_file {
+        must_not_match.extend(read_examples(path)?);
+    }
+    Ok((must_match, must_not_match))
+}
+
+/// The generate command `cli` asks for, in `flavor`.
+fn generate_command(cli: &Cli, flavor: Flavor) -> Result<GenerateCommand> {
+    let (must_match, must_not_match) = user_examples(cli)?;
+    Ok(GenerateCommand::new(flavor)
+        .with_retries(cli.retries)
+        .with_examples(must_match, must_not_match))
+}
 
-    let input = cli
-        .input
-        .ok_or_else(|| Error::InvalidFlags("No input provided".to_string()))?;
+fn generate_verified<B: Backend + ?Sized>(
+    cli: &Cli,
+    backend: &B,
+    description: &str,
+) -> Result<Generation> {
+    let cmd = generate_command(cli, cli.flavor)?;
+    let gen = cmd.generate(backend, description)?;
+    warn_unverified(&gen);
+    Ok(gen)
+}
 
-    let claude = claude::Claude::default();
+fn warn_unverified(gen: &Generation) {
+    if gen.verification.failed() {
+        eprintln!(
+            "{}: pattern still fails local verification after {} attempt(s):\n{}",
+            "warning".yellow(),
+            gen.attempts,
+            gen.verification.describe_failures()
+        );
+    }
+}
+
+/// Remember the conversation behind `gen` so `rgx refine` can resume it from
+/// this directory. Replayed sessions were never opened, so they are skipped.
+fn remember(cli: &Cli, state: &State, gen: &Generation, flavor: Flavor, description: &str) {
+    if cli.replay.is_some() {
+        return;
+    }
+    let (Some(store), Some(session_id)) = (&state.sessions, &gen.metadata.session_id) else {
+        return;
+    };
+    let Ok(dir) = std::env::current_dir() else {
+        return;
+    };
+    let _ = store.set(
+        &dir,
+        Session {
+            session_id: session_id.clone(),
+            backend: gen.metadata.backend.clone(),
+            model: gen.metadata.model.clone(),
+            flavor,
+            description: description.to_string(),
+            pattern: gen.response.pattern.clone(),
+        },
+    );
+}
+
+/// Add what the run spent to the ledger and, with `--stats`, print it.
+fn report(cli: &Cli, state: &State, metadata: &Metadata) {
+    charge(cli, state, metadata);
+    if cli.stats {
+        eprintln!("{}", output::format_usage(metadata));
+    }
+}
+
+/// Add `metadata`'s usage to the ledger. Replayed runs show their recorded
+/// usage but spent nothing.
+fn charge(cli: &Cli, state: &State, metadata: &Metadata) {
+    if let (Some(ledger), None) = (&state.ledger, &cli.replay) {
+        let _ = ledger.record(metadata);
+    }
+}
+
+/// Run `command` on `backend`. A successful run reports its own usage; one
+/// that fails is still charged for what its backend calls spent.
+fn charged<B: Backend + ?Sized, T>(
+    cli: &Cli,
+    state: &State,
+    backend: &B,
+    command: impl FnOnce(&Metered<B>) -> Result<T>,
+) -> Result<T> {
+    let metered = Metered::new(backend);
+    let result = command(&metered);
+    if let (Err(_), Some(spent)) = (&result, metered.spent()) {
+        charge(cli, state, &spent);
+    }
+    result
+}
+
+/// Output for a generated pattern: tested against `-t` input when given.
+fn render_generation(cli: &Cli, gen: &Generation) -> Result<String> {
+    if let Some(test_input) = &cli.test {
+        let test_cmd = TestCommand::new(test_input, gen.flavor);
+        let result = test_cmd.test_pattern(&gen.response)?;
+        Ok(output::format_test(
+            &result,
+            &gen.metadata,
+            gen.flavor,
+            cli.raw,
+        ))
+    } else {
+        Ok(output::format_generate(gen, cli.raw))
+    }
+}
+
+fn input(cli: &Cli) -> Result<&str> {
+    cli.input
+        .as_deref()
+        .ok_or_else(|| Error::InvalidFlags("No input provided".to_string()))
+}
+
+fn run<B: Backend + ?Sized>(cli: &Cli, backend: &B, state: &State) -> Result<String> {
+    validate_flags(cli)?;
+    let input = input(cli)?;
 
     if cli.explain {
-        let cmd = ExplainCommand::new();
-        let prompt = cmd.build_prompt(&input);
-        let response = claude.query(&prompt)?;
-        let parsed = cmd.parse_response(&response)?;
-        println!("{}", output::format_explain(&parsed, cli.raw));
-    } else if let Some(test_input) = &cli.test {
-        let gen_cmd = GenerateCommand::new(cli.flavor.as_str());
-        let prompt = gen_cmd.build_prompt(&input);
-        let response = claude.query(&prompt)?;
-        let generated = gen_cmd.parse_response(&response)?;
-
-        let test_cmd = TestCommand::new(test_input);
-        let result = test_cmd.test_pattern(&generated)?;
-        println!("{}", output::format_test(&result, cli.raw));
+        let cmd = ExplainCommand::new(cli.flavor);
+        let (parsed, metadata) = cmd.execute(backend, input)?;
+        report(cli, state, &metadata);
+        Ok(output::format_explain(
+            &parsed, &metadata, cli.flavor, cli.raw,
+        ))
     } else {
-        let cmd = GenerateCommand::new(cli.flavor.as_str());
-        let prompt = cmd.build_prompt(&input);
-        let response = claude.query(&prompt)?;
-        let parsed = cmd.parse_response(&response)?;
-        println!("{}", output::format_generate(&parsed, cli.raw));
+        let gen = generate_verified(cli, backend, input)?;
+        report(cli, state, &gen.metadata);
+        remember(cli, state, &gen, cli.flavor, input);
+        render_generation(cli, &gen)
     }
+}
 
-    Ok(())
+/// The session saved by the last generate in the working directory.
+fn last_session(sessions: Option<&SessionStore>) -> Result<Session> {
+    let store = sessions
+        .ok_or_else(|| Error::Session("no state directory to read sessions from".to_string()))?;
+    store.get(&std::env::current_dir()?)?.ok_or_else(|| {
+        Error::Session(
+            "no previous pattern to refine in this directory; generate one first".to_string(),
+        )
+    })
+}
+
+/// `rgx refine`: resume `session` so the model adjusts its last pattern.
+fn run_refine<B: Backend + ?Sized>(
+    cli: &Cli,
+    backend: &B,
+    state: &State,
+    session: &Session,
+    instruction: &str,
+) -> Result<String> {
+    let cmd = generate_command(cli, session.flavor)?;
+    let gen = cmd.refine(backend, &session.session_id, instruction)?;
+    warn_unverified(&gen);
+    report(cli, state, &gen.metadata);
+    remember(cli, state, &gen, session.flavor, &session.description);
+    render_generation(cli, &gen)
+}
+
+fn refine(cli: &mut Cli, state: &State, instruction: &str) -> Result<String> {
+    let session = last_session(state.sessions.as_ref())?;
+    if cli.dry_run {
+        return Ok(generate_command(cli, session.flavor)?.build_refine_prompt(instruction));
+    }
+    // Follow-ups must reach the backend and model that hold the conversation
+    if cli.backend.is_none() {
+        cli.backend = BackendKind::from_str(&session.backend, true).ok();
+    }
+    cli.model.get_or_insert_with(|| session.model.clone());
+
+    let config = Config::load()?;
+    let backend = build_backend(cli, &config)?;
+    charged(cli, state, backend.as_ref(), |backend| {
+        run_refine(cli, backend, state, &session, instruction)
+    })
+}
+
+/// `--dry-run`: the prompt `run` would send first.
+fn dry_run(cli: &Cli) -> Result<String> {
+    validate_flags(cli)?;
+    let input = input(cli)?;
+
+    if cli.explain {
+        Ok(ExplainCommand::new(cli.flavor).build_prompt(input))
+    } else {
+        Ok(generate_command(cli, cli.flavor)?.build_prompt(input))
+    }
+}
+
+/// `rgx stats`: monthly totals from the ledger.
+fn run_stats(cli: &Cli, state: &State) -> Result<String> {
+    let months = match &state.ledger {
+        Some(ledger) => ledger.monthly()?,
+        None => Vec::new(),
+    };
+    Ok(output::format_ledger(&months, cli.raw))
+}
+
+/// `--infer`: synthesize a pattern from the examples alone, without a backend.
+fn run_infer(cli: &Cli) -> Result<String> {
+    validate_flags(cli)?;
+
+    let (must_match, must_not_match) = user_examples(cli)?;
+    let response = infer::infer(&must_match, &must_not_match, cli.flavor)?;
+    let gen = Generation {
+        verification: verify::verify(&response, cli.flavor),
+        response,
+        flavor: cli.flavor,
+        metadata: Metadata {
+            backend: "infer".to_string(),
+            model: "local".to_string(),
+            ..Metadata::default()
+        },
+        attempts: 0,
+    };
+    render_generation(cli, &gen)
+}
+
+/// `rgx convert`: rewrite `pattern` locally, then ask the model for
+/// whatever needs a semantic rewrite. Returns the report and whether the
+/// translation is possible.
+fn convert(
+    cli: &Cli,
+    state: &State,
+    pattern: &str,
+    from: Flavor,
+    to: Flavor,
+) -> Result<(String, bool)> {
+    let conversion = rewrite::convert(pattern, from, to);
+    if conversion.remaining.is_empty() {
+        let out = output::format_convert(&conversion, None, cli.raw);
+        return Ok((out, conversion.possible));
+    }
+    if cli.dry_run {
+        let prompt = ConvertCommand::new(&conversion).build_prompt(&conversion.pattern);
+        return Ok((prompt, true));
+    }
+
+    let config = Config::load()?;
+    let backend = build_backend(cli, &config)?;
+    charged(cli, state, backend.as_ref(), |backend| {
+        run_convert(cli, backend, state, conversion)
+    })
+}
+
+fn run_convert<B: Backend + ?Sized>(
+    cli: &Cli,
+    backend: &B,
+    state: &State,
+    mut conversion: Conversion,
+) -> Result<(String, bool)> {
+    let cmd = ConvertCommand::new(&conversion);
+    let (response, metadata) = cmd.execute(backend, &conversion.pattern)?;
+    report(cli, state, &metadata);
+    cmd.apply(&mut conversion, response);
+    let out = output::format_convert(&conversion, Some(&metadata), cli.raw);
+    Ok((out, conversion.possible))
+}
+
+/// Print `out` and exit, with status 1 unless `ok`, for commands whose
+/// findings are their result.
+fn finish(out: &str, ok: bool) -> ! {
+    println!("{}", out);
+    std::process::exit(i32::from(!ok))
+}
+
+/// Exit status for a failed run: 124 on timeout and 130 on Ctrl-C, matching
+/// `timeout(1)` and the shell, so scripts can tell them apart.
+fn exit_code(error: &Error) -> i32 {
+    match error {
+        Error::Timeout(_) => 124,
+        Error::Interrupted => 130,
+        _ => 1,
+    }
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
 
     if let Some(shell) = cli.completions {
         let mut cmd = Cli::command();
@@ -115,15 +604,53 @@ fn main() {
         return;
     }
 
-    if let Err(e) = run(cli) {
-        eprintln!("{}: {}", "error".red(), e);
-        std::process::exit(1);
+    if cli.debug {
+        log::enable();
+    }
+    process::install_interrupt_handler();
+    let state = State::load();
+    let result = match cli.command.take() {
+        Some(Commands::Refine { instruction }) => refine(&mut cli, &state, &instruction),
+        Some(Commands::Stats) => run_stats(&cli, &state),
+        Some(Commands::Check { pattern }) => {
+            let issues = check::check(&pattern, cli.flavor);
+            finish(
+                &output::format_check(&pattern, cli.flavor, &issues, cli.raw),
+                issues.is_empty(),
+            )
+        }
+        Some(Commands::Convert { pattern, from, to }) => {
+            match convert(&cli, &state, &pattern, from, to) {
+                Ok((out, possible)) => finish(&out, possible),
+                Err(e) => Err(e),
+            }
+        }
+        None if cli.infer => run_infer(&cli),
+        None if cli.dry_run => dry_run(&cli),
+        None => Config::load()
+            .and_then(|config| build_backend(&cli, &config))
+            .and_then(|backend| {
+                charged(&cli, &state, backend.as_ref(), |backend| {
+                    run(&cli, backend, &state)
+                })
+            }),
+    };
+
+    match result {
+        Ok(out) => println!("{}", out),
+        Err(e) => {
+            eprintln!("{}: {}", "error".red(), e);
+            std::process::exit(exit_code(&e));
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use backend::mock::MockBackend;
+
+    const GENERATED: &str = r#"{"pattern": "\\d+", "matches": ["123"], "non_matches": ["abc"], "explanation": "One or more digits"}"#;
 
     fn make_cli(
         input: Option<&str>,
@@ -133,11 +660,30 @@ mod tests {
         flavor: Flavor,
     ) -> Cli {
         Cli {
+            command: None,
             input: input.map(|s| s.to_string()),
             explain,
             test: test.map(|s| s.to_string()),
             raw,
             flavor,
+            model: None,
+            must_match: vec![],
+            must_not_match: vec![],
+            match_file: None,
+            no_match_file: None,
+            infer: false,
+            retries: DEFAULT_RETRIES,
+            backend: None,
+            no_cache: false,
+            refresh: false,
+            cache_ttl: None,
+            stats: false,
+            claude_bin: None,
+            record: None,
+            replay: None,
+            timeout: None,
+            debug: false,
+            dry_run: false,
             completions: None,
         }
     }
@@ -207,27 +753,645 @@ mod tests {
     }
 
     #[test]
-    fn flavor_as_str_rust() {
-        assert_eq!(Flavor::Rust.as_str(), "rust");
+    fn flavor_name_rust() {
+        assert_eq!(Flavor::Rust.name(), "rust");
     }
 
     #[test]
-    fn flavor_as_str_js() {
-        assert_eq!(Flavor::Js.as_str(), "javascript");
+    fn flavor_name_js() {
+        assert_eq!(Flavor::Js.name(), "js");
     }
 
     #[test]
-    fn flavor_as_str_pcre() {
-        assert_eq!(Flavor::Pcre.as_str(), "pcre");
+    fn flavor_name_pcre() {
+        assert_eq!(Flavor::Pcre.name(), "pcre");
     }
 
     #[test]
-    fn flavor_as_str_posix() {
-        assert_eq!(Flavor::Posix.as_str(), "posix");
+    fn flavor_name_posix() {
+        assert_eq!(Flavor::Posix.name(), "posix");
+    }
+
+    #[test]
+    fn new_flavors_parse() {
+        for (name, flavor) in [
+            ("python", Flavor::Python),
+            ("re2", Flavor::Go),
+            ("java", Flavor::Java),
+            ("dotnet", Flavor::Dotnet),
+            ("bre", Flavor::Bre),
+        ] {
+            let cli = Cli::try_parse_from(["rgx", "--flavor", name, "email"]).unwrap();
+            assert_eq!(cli.flavor, flavor);
+        }
+    }
+
+    #[test]
+    fn js_flavor_reaches_the_prompt() {
+        let cli = make_cli(Some("email"), false, None, false, Flavor::Js);
+        assert!(dry_run(&cli).unwrap().contains(&Flavor::Js.guidance()));
     }
 
     #[test]
     fn flavor_default_is_rust() {
         assert!(matches!(Flavor::default(), Flavor::Rust));
     }
+
+    #[test]
+    fn build_backend_defaults_to_claude() {
+        let cli = make_cli(Some("email"), false, None, false, Flavor::Rust);
+        let backend = build_backend(&cli, &Config::default()).unwrap();
+        assert_eq!(backend.name(), "claude");
+    }
+
+    #[test]
+    fn build_backend_flag_overrides_config() {
+        let mut cli = make_cli(Some("email"), false, None, false, Flavor::Rust);
+        cli.backend = Some(BackendKind::Claude);
+        let config = Config {
+            backend: Some(BackendKind::Anthropic),
+            ..Config::default()
+        };
+        let backend = build_backend(&cli, &config).unwrap();
+        assert_eq!(backend.name(), "claude");
+    }
+
+    #[test]
+    fn build_backend_openai_from_config() {
+        let mut cli = make_cli(Some("email"), false, None, false, Flavor::Rust);
+        cli.backend = Some(BackendKind::Openai);
+        let mut config = Config::default();
+        config.openai.model = Some("qwen2.5-coder".to_string());
+        let backend = build_backend(&cli, &config).unwrap();
+        assert_eq!(backend.name(), "openai");
+        assert_eq!(backend.model(), "qwen2.5-coder");
+    }
+
+    #[test]
+    fn model_flag_overrides_config() {
+        let mut cli = make_cli(Some("email"), false, None, false, Flavor::Rust);
+        cli.model = Some("opus".to_string());
+        let config = Config {
+            model: Some("sonnet".to_string()),
+            ..Config::default()
+        };
+        let backend = build_backend(&cli, &config).unwrap();
+        assert_eq!(backend.model(), "opus");
+    }
+
+    #[test]
+    fn model_from_config_when_no_flag() {
+        let cli = make_cli(Some("email"), false, None, false, Flavor::Rust);
+        let config = Config {
+            model: Some("sonnet".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            resolve_model(&cli, &config, BackendKind::Claude, None),
+            "sonnet"
+        );
+        // RGX_MODEL beats the config file, --model beats both
+        let env = || Some("opus".to_string());
+        assert_eq!(
+            resolve_model(&cli, &config, BackendKind::Claude, env()),
+            "opus"
+        );
+        let mut cli = cli;
+        cli.model = Some("haiku".to_string());
+        assert_eq!(
+            resolve_model(&cli, &config, BackendKind::Claude, env()),
+            "haiku"
+        );
+    }
+
+    #[test]
+    fn model_defaults_per_backend() {
+        let cli = make_cli(Some("email"), false, None, false, Flavor::Rust);
+        let config = Config::default();
+        assert_eq!(
+            resolve_model(&cli, &config, BackendKind::Claude, None),
+            "haiku"
+        );
+        assert_eq!(
+            resolve_model(&cli, &config, BackendKind::Openai, None),
+            backend::openai::DEFAULT_MODEL
+        );
+        assert_eq!(
+            resolve_model(
+                &cli,
+                &config,
+                BackendKind::Openai,
+                Some("llama3".to_string())
+            ),
+            "llama3"
+        );
+    }
+
+    #[test]
+    fn run_records_model_in_raw_output() {
+        let backend = MockBackend::new([GENERATED]);
+        let cli = make_cli(Some("digits"), false, None, true, Flavor::Rust);
+        let out = run(&cli, &backend, &State::default()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["model"], "mock-model");
+    }
+
+    #[test]
+    fn run_generate_retries_failed_examples() {
+        let failing = r#"{"pattern": "^\\d$", "matches": ["123"], "non_matches": ["abc"], "explanation": "digits"}"#;
+        let backend = MockBackend::new([failing, GENERATED]);
+        let cli = make_cli(Some("digits"), false, None, true, Flavor::Rust);
+        let out = run(&cli, &backend, &State::default()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["pattern"], "\\d+");
+        assert_eq!(json["verification"]["passed"], true);
+        assert_eq!(backend.prompts().len(), 2);
+    }
+
+    #[test]
+    fn validate_flags_explain_with_examples_invalid() {
+        let mut cli = make_cli(Some(r"\d+"), true, None, false, Flavor::Rust);
+        cli.must_match = vec!["1".to_string()];
+        let err = validate_flags(&cli).unwrap_err();
+        assert!(err.to_string().contains("--match"));
+    }
+
+    #[test]
+    fn user_examples_combine_flags_and_files() {
+        let dir = std::env::temp_dir();
+        let match_file = dir.join(format!("rgx-match-{}.txt", std::process::id()));
+        fs::write(&match_file, "456\n\n789\n").unwrap();
+
+        let mut cli = make_cli(Some("digits"), false, None, false, Flavor::Rust);
+        cli.must_match = vec!["123".to_string()];
+        cli.must_not_match = vec!["abc".to_string()];
+        cli.match_file = Some(match_file.clone());
+        let (must_match, must_not_match) = user_examples(&cli).unwrap();
+        fs::remove_file(&match_file).unwrap();
+
+        assert_eq!(must_match, vec!["123", "456", "789"]);
+        assert_eq!(must_not_match, vec!["abc"]);
+    }
+
+    #[test]
+    fn user_examples_missing_file_is_error() {
+        let mut cli = make_cli(Some("digits"), false, None, false, Flavor::Rust);
+        cli.no_match_file = Some(PathBuf::from("/nonexistent/rgx-examples.txt"));
+        assert!(matches!(user_examples(&cli), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn run_generate_sends_user_examples() {
+        let backend = MockBackend::new([GENERATED]);
+        let mut cli = make_cli(Some("digits"), false, None, true, Flavor::Rust);
+        cli.must_match = vec!["987".to_string()];
+        let out = run(&cli, &backend, &State::default()).unwrap();
+        assert!(backend.prompts()[0].contains("\"987\""));
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["matches"][1], "987");
+        assert_eq!(json["verification"]["matches"][1]["actual"], true);
+    }
+
+    #[test]
+    fn validate_flags_explain_and_infer_invalid() {
+        let mut cli = make_cli(Some(r"\d+"), true, None, false, Flavor::Rust);
+        cli.infer = true;
+        assert!(validate_flags(&cli).is_err());
+    }
+
+    #[test]
+    fn run_infer_without_description() {
+        let mut cli = make_cli(None, false, None, true, Flavor::Rust);
+        cli.infer = true;
+        cli.must_match = vec!["2024-01-15".to_string(), "1999-12-31".to_string()];
+        cli.must_not_match = vec!["2024-1-15".to_string()];
+        let out = run_infer(&cli).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["pattern"], r"^\d{4}-\d{2}-\d{2}$");
+        assert_eq!(json["backend"], "infer");
+        assert_eq!(json["verification"]["passed"], true);
+    }
+
+    #[test]
+    fn run_infer_test_mode() {
+        let mut cli = make_cli(None, false, Some("id: 42"), true, Flavor::Rust);
+        cli.infer = true;
+        cli.must_match = vec!["7".to_string(), "123".to_string()];
+        let out = run_infer(&cli).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["pattern"], r"^\d{1,3}$");
+        assert_eq!(json["matches"], false);
+    }
+
+    #[test]
+    fn run_infer_requires_examples() {
+        let mut cli = make_cli(None, false, None, false, Flavor::Rust);
+        cli.infer = true;
+        assert!(run_infer(&cli).is_err());
+    }
+
+    #[test]
+    fn refresh_conflicts_with_no_cache() {
+        assert!(Cli::try_parse_from(["rgx", "--no-cache", "--refresh", "email"]).is_err());
+        assert!(Cli::try_parse_from(["rgx", "--refresh", "email"]).is_ok());
+    }
+
+    #[test]
+    fn run_requires_input() {
+        let backend = MockBackend::default();
+        let cli = make_cli(None, false, None, false, Flavor::Rust);
+        let err = run(&cli, &backend, &State::default()).unwrap_err();
+        assert!(err.to_string().contains("No input provided"));
+        assert!(backend.prompts().is_empty());
+    }
+
+    #[test]
+    fn run_generate_with_mock_backend() {
+        let backend = MockBackend::new([GENERATED]);
+        let cli = make_cli(Some("digits"), false, None, true, Flavor::Rust);
+        let out = run(&cli, &backend, &State::default()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["pattern"], "\\d+");
+        assert!(backend.prompts()[0].contains("digits"));
+    }
+
+    #[test]
+    fn run_explain_with_mock_backend() {
+        let backend = MockBackend::new([
+            r#"{"tokens": [{"token": "\\d+", "explanation": "digits"}], "purpose": "numbers"}"#,
+        ]);
+        let cli = make_cli(Some(r"\d+"), true, None, true, Flavor::Rust);
+        let out = run(&cli, &backend, &State::default()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["purpose"], "numbers");
+    }
+
+    #[test]
+    fn run_test_mode_with_mock_backend() {
+        let backend = MockBackend::new([GENERATED]);
+        let cli = make_cli(Some("digits"), false, Some("abc 42"), true, Flavor::Rust);
+        let out = run(&cli, &backend, &State::default()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["matches"], true);
+        assert_eq!(json["match_details"]["full_match"], "42");
+    }
+
+    fn state(dir: &tempfile::TempDir) -> State {
+        State {
+            sessions: Some(SessionStore::new(dir.path().join("sessions.json"))),
+            ledger: Some(Ledger::new(dir.path().join("ledger.jsonl"))),
+        }
+    }
+
+    #[test]
+    fn refine_subcommand_parses_with_flags() {
+        let cli = Cli::try_parse_from(["rgx", "refine", "allow dashes", "--raw"]).unwrap();
+        assert!(cli.raw);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Refine { instruction }) if instruction == "allow dashes"
+        ));
+    }
+
+    #[test]
+    fn run_generate_remembers_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state(&dir);
+        let backend = MockBackend::new([GENERATED]).with_session("abc");
+        let cli = make_cli(Some("digits"), false, None, false, Flavor::Pcre);
+        run(&cli, &backend, &state).unwrap();
+
+        let session = last_session(state.sessions.as_ref()).unwrap();
+        assert_eq!(session.session_id, "abc");
+        assert_eq!(session.backend, "mock");
+        assert_eq!(session.flavor, Flavor::Pcre);
+        assert_eq!(session.description, "digits");
+        assert_eq!(session.pattern, "\\d+");
+    }
+
+    #[test]
+    fn replay_does_not_remember_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state(&dir);
+        let backend = MockBackend::new([GENERATED]).with_session("abc");
+        let mut cli = make_cli(Some("digits"), false, None, false, Flavor::Pcre);
+        cli.replay = Some(dir.path().to_path_buf());
+        run(&cli, &backend, &state).unwrap();
+        assert!(last_session(state.sessions.as_ref()).is_err());
+    }
+
+    #[test]
+    fn run_generate_without_session_id_stores_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state(&dir);
+        let backend = MockBackend::new([GENERATED]);
+        let cli = make_cli(Some("digits"), false, None, false, Flavor::Rust);
+        run(&cli, &backend, &state).unwrap();
+        assert!(last_session(state.sessions.as_ref()).is_err());
+    }
+
+    #[test]
+    fn refine_without_previous_pattern_is_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = last_session(state(&dir).sessions.as_ref()).unwrap_err();
+        assert!(err.to_string().contains("no previous pattern"));
+    }
+
+    #[test]
+    fn run_refine_resumes_and_updates_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state(&dir);
+        let backend = MockBackend::new([GENERATED]).with_session("first");
+        let cli = make_cli(Some("digits"), false, None, false, Flavor::Rust);
+        run(&cli, &backend, &state).unwrap();
+
+        let refined = r#"{"pattern": "-?\\d+", "matches": ["-1"], "non_matches": ["a"], "explanation": "signed"}"#;
+        let backend = MockBackend::new([refined]).with_session("second");
+        let cli = make_cli(None, false, None, true, Flavor::Rust);
+        let session = last_session(state.sessions.as_ref()).unwrap();
+        let out = run_refine(&cli, &backend, &state, &session, "allow a sign").unwrap();
+
+        assert_eq!(backend.resumed(), vec!["first"]);
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["pattern"], "-?\\d+");
+        let session = last_session(state.sessions.as_ref()).unwrap();
+        assert_eq!(session.session_id, "second");
+        assert_eq!(session.pattern, "-?\\d+");
+        assert_eq!(session.description, "digits");
+    }
+
+    #[test]
+    fn dry_run_prints_generate_prompt() {
+        let mut cli = make_cli(Some("digits"), false, None, false, Flavor::Pcre);
+        cli.must_match = vec!["42".to_string()];
+        let prompt = dry_run(&cli).unwrap();
+        assert_eq!(
+            prompt,
+            generate_command(&cli, Flavor::Pcre)
+                .unwrap()
+                .build_prompt("digits")
+        );
+        assert!(prompt.contains("digits"));
+        assert!(prompt.contains("42"));
+    }
+
+    #[test]
+    fn dry_run_prints_explain_prompt() {
+        let cli = make_cli(Some(r"\d+"), true, None, false, Flavor::Rust);
+        assert_eq!(
+            dry_run(&cli).unwrap(),
+            ExplainCommand::new(Flavor::Rust).build_prompt(r"\d+")
+        );
+    }
+
+    #[test]
+    fn dry_run_rejects_infer() {
+        let mut cli = make_cli(Some("x"), false, None, false, Flavor::Rust);
+        cli.dry_run = true;
+        cli.infer = true;
+        assert!(run_infer(&cli).is_err());
+    }
+
+    #[test]
+    fn refine_dry_run_skips_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state(&dir);
+        let backend = MockBackend::new([GENERATED]).with_session("abc");
+        let cli = make_cli(Some("digits"), false, None, false, Flavor::Rust);
+        run(&cli, &backend, &state).unwrap();
+
+        let mut cli = make_cli(None, false, None, false, Flavor::Rust);
+        cli.dry_run = true;
+        // Never built, so a missing API key cannot fail the dry run
+        cli.backend = Some(BackendKind::Anthropic);
+        let prompt = refine(&mut cli, &state, "allow a sign").unwrap();
+        assert!(prompt.contains("allow a sign"));
+    }
+
+    /// Run `cli` once against `responses` while recording, then again from
+    /// the recording alone, and return both outputs.
+    fn record_and_replay(cli: &Cli, responses: &[&str]) -> (String, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = Recorder::new(
+            MockBackend::new(responses.iter().copied()),
+            dir.path().to_path_buf(),
+        );
+        let recorded = run(cli, &recorder, &State::default()).unwrap();
+        let replay = Replay::new(dir.path().to_path_buf());
+        let replayed = run(cli, &replay, &State::default()).unwrap();
+        (recorded, replayed)
+    }
+
+    #[test]
+    fn replay_generate_matches_recording() {
+        let failing = r#"{"pattern": "[a-z]+", "matches": ["123"], "non_matches": [], "explanation": "letters"}"#;
+        let cli = make_cli(Some("digits"), false, None, true, Flavor::Rust);
+        let (recorded, replayed) = record_and_replay(&cli, &[failing, GENERATED]);
+        assert_eq!(recorded, replayed);
+        let json: serde_json::Value = serde_json::from_str(&replayed).unwrap();
+        assert_eq!(json["pattern"], "\\d+");
+        assert_eq!(json["backend"], "mock");
+    }
+
+    #[test]
+    fn replay_explain_matches_recording() {
+        let explained =
+            r#"{"tokens": [{"token": "\\d+", "explanation": "digits"}], "purpose": "numbers"}"#;
+        let cli = make_cli(Some(r"\d+"), true, None, false, Flavor::Rust);
+        let (recorded, replayed) = record_and_replay(&cli, &[explained]);
+        assert_eq!(recorded, replayed);
+    }
+
+    #[test]
+    fn replay_test_mode_matches_recording() {
+        let cli = make_cli(Some("digits"), false, Some("abc 42"), true, Flavor::Rust);
+        let (recorded, replayed) = record_and_replay(&cli, &[GENERATED]);
+        assert_eq!(recorded, replayed);
+        let json: serde_json::Value = serde_json::from_str(&replayed).unwrap();
+        assert_eq!(json["matches"], true);
+    }
+
+    #[test]
+    fn replay_with_changed_prompt_fails() {
+        let cli = make_cli(Some("digits"), false, None, false, Flavor::Rust);
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = Recorder::new(MockBackend::new([GENERATED]), dir.path().to_path_buf());
+        run(&cli, &recorder, &State::default()).unwrap();
+
+        let cli = make_cli(Some("numbers"), false, None, false, Flavor::Rust);
+        let replay = Replay::new(dir.path().to_path_buf());
+        let err = run(&cli, &replay, &State::default()).unwrap_err();
+        assert!(matches!(err, Error::Fixture(_)));
+    }
+
+    #[test]
+    fn replay_skips_ledger() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state(&dir);
+        let mut cli = make_cli(Some("digits"), false, None, false, Flavor::Rust);
+        cli.replay = Some(dir.path().to_path_buf());
+        let metadata = Metadata {
+            usage: Some(backend::Usage::default()),
+            ..Metadata::default()
+        };
+        report(&cli, &state, &metadata);
+        assert!(state.ledger.unwrap().entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn exit_codes_distinguish_timeouts() {
+        assert_eq!(exit_code(&Error::Timeout(Duration::from_secs(1))), 124);
+        assert_eq!(exit_code(&Error::Interrupted), 130);
+        assert_eq!(exit_code(&Error::Claude("boom".to_string())), 1);
+    }
+
+    #[test]
+    fn run_records_usage_in_ledger_and_raw_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state(&dir);
+        let usage = backend::Usage {
+            input_tokens: 120,
+            output_tokens: 30,
+            cost_usd: Some(0.002),
+            duration_ms: 900,
+        };
+        let backend = MockBackend::new([GENERATED]).with_usage(usage);
+        let cli = make_cli(Some("digits"), false, None, true, Flavor::Rust);
+        let out = run(&cli, &backend, &state).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["usage"]["input_tokens"], 120);
+        assert_eq!(json["usage"]["cost_usd"], 0.002);
+
+        let months = state.ledger.as_ref().unwrap().monthly().unwrap();
+        assert_eq!(months.len(), 1);
+        assert_eq!(months[0].runs, 1);
+        assert_eq!(months[0].output_tokens, 30);
+
+        let stats = run_stats(&cli, &state).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&stats).unwrap();
+        assert_eq!(json[0]["input_tokens"], 120);
+    }
+
+    #[test]
+    fn failed_runs_are_charged() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state(&dir);
+        let usage = backend::Usage {
+            input_tokens: 100,
+            output_tokens: 10,
+            cost_usd: Some(0.01),
+            duration_ms: 500,
+        };
+        let backend = MockBackend::new(["not json", "still not", "nope"]).with_usage(usage);
+        let cli = make_cli(Some("digits"), false, None, true, Flavor::Rust);
+        let result = charged(&cli, &state, &backend, |backend| run(&cli, backend, &state));
+        assert!(matches!(result, Err(Error::Parse(_))));
+
+        let months = state.ledger.as_ref().unwrap().monthly().unwrap();
+        assert_eq!(months[0].runs, 1);
+        assert_eq!(months[0].input_tokens, 300);
+    }
+
+    #[test]
+    fn stats_subcommand_parses() {
+        let cli = Cli::try_parse_from(["rgx", "stats", "--raw"]).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Stats)));
+        assert!(cli.raw);
+    }
+
+    #[test]
+    fn check_subcommand_parses() {
+        let cli = Cli::try_parse_from(["rgx", "check", "--flavor", "posix", r"\d+"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Check { ref pattern }) if pattern == r"\d+"
+        ));
+        assert_eq!(cli.flavor, Flavor::Posix);
+    }
+
+    #[test]
+    fn check_takes_the_flavor_before_the_subcommand() {
+        let cli = Cli::try_parse_from(["rgx", "--flavor", "posix", "check", r"\d+"]).unwrap();
+        assert_eq!(cli.flavor, Flavor::Posix);
+    }
+
+    #[test]
+    fn convert_subcommand_parses() {
+        let cli =
+            Cli::try_parse_from(["rgx", "convert", "--from", "pcre", "--to", "rust", "x"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Convert {
+                from: Flavor::Pcre,
+                to: Flavor::Rust,
+                ..
+            })
+        ));
+        assert!(Cli::try_parse_from(["rgx", "convert", "--to", "rust", "x"]).is_err());
+    }
+
+    #[test]
+    fn local_convert_needs_no_backend() {
+        let cli = Cli::try_parse_from([
+            "rgx", "--raw", "convert", "--from", "pcre", "--to", "js", "x",
+        ])
+        .unwrap();
+        let (out, possible) = convert(
+            &cli,
+            &State::default(),
+            r"(?P<n>\d)\Z",
+            Flavor::Pcre,
+            Flavor::Js,
+        )
+        .unwrap();
+        assert!(possible);
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["pattern"], r"(?<n>\d)$");
+        assert_eq!(json["lossy"], true);
+    }
+
+    #[test]
+    fn convert_dry_run_prints_model_prompt() {
+        let cli = Cli::try_parse_from([
+            "rgx",
+            "--dry-run",
+            "convert",
+            "--from",
+            "pcre",
+            "--to",
+            "posix",
+            "x",
+        ])
+        .unwrap();
+        let (out, _) = convert(
+            &cli,
+            &State::default(),
+            r"(?<=a)\d",
+            Flavor::Pcre,
+            Flavor::Posix,
+        )
+        .unwrap();
+        assert!(out.contains("<pattern>\n(?<=a)[[:digit:]]\n</pattern>"));
+        assert!(out.contains("lookbehind"));
+    }
+
+    #[test]
+    fn model_finishes_conversion() {
+        let cli = Cli::try_parse_from([
+            "rgx", "--raw", "convert", "--from", "pcre", "--to", "rust", "x",
+        ])
+        .unwrap();
+        let backend = MockBackend::new([
+            r#"{"pattern": "a(b)", "possible": true, "lossy": true, "notes": ["b is captured instead of looked ahead"]}"#,
+        ]);
+        let conversion = rewrite::convert("a(?=b)", Flavor::Pcre, Flavor::Rust);
+        let (out, possible) = run_convert(&cli, &backend, &State::default(), conversion).unwrap();
+        assert!(possible);
+        assert!(backend.prompts()[0].contains("lookahead (?= is not supported"));
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["pattern"], "a(b)");
+        assert_eq!(json["lossy"], true);
+        assert_eq!(json["backend"], "mock");
+    }
 }
@@ -1,10 +1,32 @@
+use crate::backend::Metadata;
+use crate::check::{self, Issue};
 use crate::commands::explain::ExplainResponse;
-use crate::commands::generate::GenerateResponse;
+use crate::commands::generate::{GenerateResponse, Generation};
 use crate::commands::test::TestResult;
+use crate::flavor::Flavor;
+use crate::ledger::MonthSummary;
+use crate::rewrite::Conversion;
+use crate::verify::{ExampleCheck, Verification};
 use crossterm::style::Stylize;
+use serde::Serialize;
 
-/// Colorize a regex pattern for terminal display
-pub fn colorize_regex(pattern: &str) -> String {
+/// `--raw` output: the command result with the backend metadata alongside it.
+#[derive(Serialize)]
+struct RawOutput<'a, T: Serialize> {
+    #[serde(flatten)]
+    result: &'a T,
+    #[serde(flatten)]
+    metadata: &'a Metadata,
+}
+
+fn format_raw<T: Serialize>(result: &T, metadata: &Metadata) -> String {
+    serde_json::to_string_pretty(&RawOutput { result, metadata }).unwrap_or_default()
+}
+
+/// Colorize a regex pattern for terminal display. Escapes `flavor` does not
+/// support are left uncolored, since its engine reads them as literals.
+pub fn colorize_regex(pattern: &str, flavor: Flavor) -> String {
+    let caps = flavor.capabilities();
     let mut result = String::new();
     let chars: Vec<char> = pattern.chars().collect();
     let mut i = 0;
@@ -16,12 +38,33 @@ pub fn colorize_regex(pattern: &str) -> String {
             let escaped = format!("\\{}", next);
 
             match next {
+                // Basic syntax groups \( \) - green
+                '(' | ')' if caps.basic_syntax => {
+                    result.push_str(&escaped.green().to_string());
+                }
+                // Basic syntax intervals \{n,m\} - yellow
+                '{' if caps.basic_syntax => {
+                    let interval = basic_interval(&chars[i..]);
+                    i += interval.chars().count();
+                    result.push_str(&interval.yellow().to_string());
+                    continue;
+                }
                 // Character classes - cyan
-                'd' | 'D' | 'w' | 'W' | 's' | 'S' => {
+                'd' | 'D' | 'w' | 'W' | 's' | 'S' if caps.shorthand_classes => {
                     result.push_str(&escaped.cyan().to_string());
                 }
+                // Unicode property classes \p{L} - cyan
+                'p' | 'P' if caps.unicode_classes => {
+                    let class = unicode_class(&chars[i..]);
+                    i += class.chars().count();
+                    result.push_str(&class.cyan().to_string());
+                    continue;
+                }
                 // Anchors - red
-                'b' | 'B' | 'A' | 'z' | 'Z' => {
+                'b' | 'B' if caps.word_boundaries => {
+                    result.push_str(&escaped.red().to_string());
+                }
+                'A' | 'z' | 'Z' if check::Construct::StringAnchor.supported(&escaped, caps) => {
                     result.push_str(&escaped.red().to_string());
                 }
                 // Other escapes - just show them normally
@@ -40,22 +83,22 @@ pub fn colorize_regex(pattern: &str) -> String {
             continue;
         }
 
-        // Quantifiers - yellow
-        if chars[i] == '+' || chars[i] == '*' || chars[i] == '?' {
+        // Quantifiers - yellow; basic syntax only has *
+        if chars[i] == '*' || (!caps.basic_syntax && (chars[i] == '+' || chars[i] == '?')) {
             result.push_str(&chars[i].to_string().yellow().to_string());
             i += 1;
             continue;
         }
 
         // Alternation - yellow
-        if chars[i] == '|' {
+        if chars[i] == '|' && !caps.basic_syntax {
             result.push_str(&"|".yellow().to_string());
             i += 1;
             continue;
         }
 
         // Quantifier braces {n,m} - yellow
-        if chars[i] == '{' {
+        if chars[i] == '{' && !caps.basic_syntax {
             let mut end = i + 1;
             while end < chars.len() && chars[end] != '}' {
                 end += 1;
@@ -83,6 +126,16 @@ pub fn colorize_regex(pattern: &str) -> String {
             while end < chars.len() && chars[end] != ']' {
                 if chars[end] == '\\' && end + 1 < chars.len() {
                     end += 2;
+                } else if chars[end] == '[' && matches!(chars.get(end + 1), Some(':' | '=' | '.')) {
+                    // [:alpha:] and the like end in a ] that does not close the set
+                    let delimiter = chars[end + 1];
+                    end += 2;
+                    while end + 1 < chars.len()
+                        && !(chars[end] == delimiter && chars[end + 1] == ']')
+                    {
+                        end += 1;
+                    }
+                    end += 2;
                 } else {
                     end += 1;
                 }
@@ -95,7 +148,12 @@ pub fn colorize_regex(pattern: &str) -> String {
             }
         }
 
-        // Groups - green
+        // Groups - green; literal parentheses in basic syntax
+        if caps.basic_syntax {
+            result.push(chars[i]);
+            i += 1;
+            continue;
+        }
         if chars[i] == '(' {
             result.push_str(&"(".green().to_string());
             i += 1;
@@ -115,35 +173,138 @@ pub fn colorize_regex(pattern: &str) -> String {
     result
 }
 
-pub fn format_generate(resp: &GenerateResponse, raw: bool) -> String {
+/// `\{n,m\}` at the start of `chars`, or just `\{` when it is not closed.
+fn basic_interval(chars: &[char]) -> String {
+    let len = chars
+        .windows(2)
+        .position(|pair| pair == ['\\', '}'])
+        .map_or(2, |end| end + 2);
+    chars[..len].iter().collect()
+}
+
+/// `\pL` or `\p{Greek}` at the start of `chars`.
+fn unicode_class(chars: &[char]) -> String {
+    let len = match chars.get(2) {
+        Some('{') => chars
+            .iter()
+            .position(|&c| c == '}')
+            .map_or(chars.len(), |end| end + 1),
+        Some(_) => 3,
+        None => 2,
+    };
+    chars[..len].iter().collect()
+}
+
+/// `--raw` generate output: the response plus the local verification report.
+#[derive(Serialize)]
+struct GenerateReport<'a> {
+    #[serde(flatten)]
+    response: &'a GenerateResponse,
+    verification: &'a Verification,
+    /// Constructs the target flavor does not support
+    #[serde(skip_serializing_if = "<[Issue]>::is_empty")]
+    issues: &'a [Issue],
+}
+
+/// ✓ when the local engine agrees with the example's classification, ✗ otherwise.
+fn verification_mark(check: Option<&ExampleCheck>) -> String {
+    match check {
+        Some(check) if check.passed() => format!(" {}", "✓".green()),
+        Some(check) if check.expected => format!(" {} {}", "✗".red(), "(no match)".dark_grey()),
+        Some(_) => format!(" {} {}", "✗".red(), "(matches)".dark_grey()),
+        None => String::new(),
+    }
+}
+
+pub fn format_generate(gen: &Generation, raw: bool) -> String {
+    let resp = &gen.response;
+    let verification = &gen.verification;
+    let issues = check::check(&resp.pattern, gen.flavor);
+
     if raw {
-        return serde_json::to_string_pretty(resp).unwrap_or_default();
+        let report = GenerateReport {
+            response: resp,
+            verification,
+            issues: &issues,
+        };
+        return format_raw(&report, &gen.metadata);
     }
 
     let mut out = String::new();
 
     out.push_str(&format!("{}\n", "Pattern:".bold()));
-    out.push_str(&format!("  {}\n", colorize_regex(&resp.pattern)));
+    out.push_str(&format!(
+        "  {}\n",
+        colorize_regex(&resp.pattern, gen.flavor)
+    ));
+
+    for issue in &issues {
+        out.push_str(&format!(
+            "  {} {}\n",
+            "warning:".yellow().bold(),
+            issue.message
+        ));
+    }
 
     out.push_str(&format!("\n{}\n", "Explanation:".bold()));
     out.push_str(&format!("  {}\n", resp.explanation));
 
     out.push_str(&format!("\n{}\n", "Matches:".green().bold()));
-    for example in &resp.matches {
-        out.push_str(&format!("  {} {}\n", "+".green(), example));
+    for (i, example) in resp.matches.iter().enumerate() {
+        out.push_str(&format!(
+            "  {} {}{}\n",
+            "+".green(),
+            example,
+            verification_mark(verification.matches.get(i))
+        ));
     }
 
     out.push_str(&format!("\n{}\n", "Non-matches:".red().bold()));
-    for example in &resp.non_matches {
-        out.push_str(&format!("  {} {}\n", "-".red(), example));
+    for (i, example) in resp.non_matches.iter().enumerate() {
+        out.push_str(&format!(
+            "  {} {}{}\n",
+            "-".red(),
+            example,
+            verification_mark(verification.non_matches.get(i))
+        ));
+    }
+
+    if verification.unsupported {
+        out.push_str(&format!(
+            "\n{} {} cannot run this pattern, though {} allows it\n",
+            "Not verified:".yellow().bold(),
+            verification.engine,
+            gen.flavor.label()
+        ));
+    } else if let Some(error) = &verification.error {
+        // An unsupported construct fails verification with its check
+        // message, which the warnings above already print
+        if issues.first().map(|issue| &issue.message) != Some(error) {
+            out.push_str(&format!(
+                "\n{} {} ({})\n",
+                "Not verified:".yellow().bold(),
+                error.lines().last().unwrap_or_default(),
+                verification.engine
+            ));
+        }
+    } else {
+        out.push_str(&format!(
+            "\n{}\n",
+            format!("Verified with {}", verification.engine).dark_grey()
+        ));
     }
 
     out
 }
 
-pub fn format_explain(resp: &ExplainResponse, raw: bool) -> String {
+pub fn format_explain(
+    resp: &ExplainResponse,
+    metadata: &Metadata,
+    flavor: Flavor,
+    raw: bool,
+) -> String {
     if raw {
-        return serde_json::to_string_pretty(resp).unwrap_or_default();
+        return format_raw(resp, metadata);
     }
 
     let mut out = String::new();
@@ -152,7 +313,7 @@ pub fn format_explain(resp: &ExplainResponse, raw: bool) -> String {
     for token in &resp.tokens {
         out.push_str(&format!(
             "  {} {} {}\n",
-            colorize_regex(&token.token),
+            colorize_regex(&token.token, flavor),
             "→".dark_grey(),
             token.explanation
         ));
@@ -164,15 +325,19 @@ pub fn format_explain(resp: &ExplainResponse, raw: bool) -> String {
     out
 }
 
-pub fn format_test(result: &TestResult, raw: bool) -> String {
+pub fn format_test(result: &TestResult, metadata: &Metadata, flavor: Flavor, raw: bool) -> String {
     if raw {
-        return serde_json::to_string_pretty(result).unwrap_or_default();
+        return format_raw(result, metadata);
     }
 
     let mut out = String::new();
 
     out.push_str(&format!("{}\n", "Pattern:".bold()));
-    out.push_str(&format!("  {}\n", colorize_regex(&result.pattern)));
+    out.push_str(&format!("  {}\n", colorize_regex(&result.pattern, flavor)));
+    out.push_str(&format!(
+        "  {}\n",
+        format!("engine: {}", result.engine).dark_grey()
+    ));
 
     out.push_str(&format!("\n{}\n", "Test Input:".bold()));
     out.push_str(&format!("  \"{}\"\n", result.test_input));
@@ -218,19 +383,238 @@ pub fn format_test(result: &TestResult, raw: bool) -> String {
     out
 }
 
+/// `--stats` line: what the run cost, or why nothing was spent.
+pub fn format_usage(metadata: &Metadata) -> String {
+    let label = "stats:".dark_grey();
+    if metadata.cached {
+        return format!("{} served from cache, nothing spent", label);
+    }
+    let Some(usage) = &metadata.usage else {
+        return format!("{} no usage reported by {}", label, metadata.backend);
+    };
+    let cost = usage
+        .cost_usd
+        .map(|cost| format!("${:.4}", cost))
+        .unwrap_or_else(|| "unknown".to_string());
+    format!(
+        "{} {} tokens in, {} out, cost {}, {:.1}s",
+        label,
+        usage.input_tokens,
+        usage.output_tokens,
+        cost,
+        usage.duration_ms as f64 / 1000.0
+    )
+}
+
+/// `--raw` check output.
+#[derive(Serialize)]
+struct CheckReport<'a> {
+    pattern: &'a str,
+    flavor: Flavor,
+    compatible: bool,
+    issues: &'a [Issue],
+}
+
+/// Report of `rgx check`: each issue under the pattern with its span
+/// underlined.
+pub fn format_check(pattern: &str, flavor: Flavor, issues: &[Issue], raw: bool) -> String {
+    if raw {
+        let report = CheckReport {
+            pattern,
+            flavor,
+            compatible: issues.is_empty(),
+            issues,
+        };
+        return serde_json::to_string_pretty(&report).unwrap_or_default();
+    }
+    if issues.is_empty() {
+        return format!(
+            "{} No constructs unsupported by {}",
+            "✓".green(),
+            flavor.label()
+        );
+    }
+
+    let mut out = format!(
+        "{}\n",
+        format!(
+            "{} construct{} unsupported by {}:",
+            issues.len(),
+            if issues.len() == 1 { "" } else { "s" },
+            flavor.label()
+        )
+        .bold()
+    );
+    for issue in issues {
+        let column = pattern[..issue.start].chars().count();
+        let width = issue.text.chars().count().max(1);
+        out.push_str(&format!(
+            "\n  {}\n  {}{} {}\n  {}\n",
+            colorize_regex(pattern, flavor),
+            " ".repeat(column),
+            "^".repeat(width).red().bold(),
+            format!("{}..{}", issue.start, issue.end).dark_grey(),
+            issue.message
+        ));
+    }
+    out
+}
+
+/// Report of `rgx convert`. `metadata` is there when the model was asked to
+/// finish the translation.
+pub fn format_convert(conversion: &Conversion, metadata: Option<&Metadata>, raw: bool) -> String {
+    if raw {
+        return match metadata {
+            Some(metadata) => format_raw(conversion, metadata),
+            None => serde_json::to_string_pretty(conversion).unwrap_or_default(),
+        };
+    }
+
+    let to = conversion.to;
+    let mut out = String::new();
+    if conversion.possible {
+        out.push_str(&format!("{}\n", format!("{}:", to.label()).bold()));
+        out.push_str(&format!("  {}\n", colorize_regex(&conversion.pattern, to)));
+        if !conversion.flags.is_empty() {
+            out.push_str(&format!(
+                "  {}\n",
+                format!(
+                    "only with the {} flags set where it is compiled",
+                    conversion.flags
+                )
+                .yellow()
+            ));
+        }
+    } else {
+        out.push_str(&format!(
+            "{}\n",
+            format!("Cannot translate to {}:", to.label()).red().bold()
+        ));
+        for issue in &conversion.remaining {
+            out.push_str(&format!("  {}\n", issue.message));
+        }
+        if conversion.pattern != conversion.source {
+            out.push_str(&format!(
+                "\n{}\n  {}\n",
+                "Partial translation:".bold(),
+                colorize_regex(&conversion.pattern, to)
+            ));
+        }
+    }
+
+    if !conversion.changes.is_empty() {
+        out.push_str(&format!("\n{}\n", "Changes:".bold()));
+        for change in &conversion.changes {
+            let mark = if change.lossy {
+                " (lossy)".yellow().to_string()
+            } else {
+                String::new()
+            };
+            out.push_str(&format!(
+                "  {} {} {}{}\n",
+                change.text,
+                "→".dark_grey(),
+                if change.replacement.is_empty() {
+                    "(removed)".dark_grey().to_string()
+                } else {
+                    change.replacement.clone()
+                },
+                mark
+            ));
+            if let Some(note) = &change.note {
+                out.push_str(&format!("    {}\n", note.clone().dark_grey()));
+            }
+        }
+    }
+
+    if !conversion.notes.is_empty() {
+        out.push_str(&format!("\n{}\n", "Notes:".bold()));
+        for note in &conversion.notes {
+            out.push_str(&format!("  {}\n", note));
+        }
+    }
+
+    if conversion.possible {
+        out.push_str(&if conversion.lossy {
+            format!(
+                "\n{} matches differently from the {} pattern on some inputs\n",
+                "Lossy:".yellow().bold(),
+                conversion.from.label()
+            )
+        } else if !conversion.flags.is_empty() {
+            format!(
+                "\n{}\n",
+                format!("Exact translation with the {} flags", conversion.flags).green()
+            )
+        } else {
+            format!("\n{}\n", "Exact translation".green())
+        });
+    }
+    out
+}
+
+/// `rgx stats`: spending per month from the ledger.
+pub fn format_ledger(months: &[MonthSummary], raw: bool) -> String {
+    if raw {
+        return serde_json::to_string_pretty(months).unwrap_or_default();
+    }
+    if months.is_empty() {
+        return "No usage recorded yet".to_string();
+    }
+
+    let mut out = format!(
+        "{}\n",
+        format!(
+            "{:<8} {:>6} {:>12} {:>12} {:>10} {:>10}",
+            "Month", "Runs", "Tokens in", "Tokens out", "Cost", "Time"
+        )
+        .bold()
+    );
+    for month in months {
+        let cost = if month.unpriced == month.runs {
+            "unknown".to_string()
+        } else if month.unpriced > 0 {
+            format!("${:.2}+", month.cost_usd)
+        } else {
+            format!("${:.2}", month.cost_usd)
+        };
+        out.push_str(&format!(
+            "{:<8} {:>6} {:>12} {:>12} {:>10} {:>10}\n",
+            month.month,
+            month.runs,
+            month.input_tokens,
+            month.output_tokens,
+            cost,
+            format!("{:.0}s", month.duration_ms as f64 / 1000.0)
+        ));
+    }
+    let unpriced: usize = months.iter().map(|month| month.unpriced).sum();
+    if unpriced > 0 {
+        out.push_str(&format!(
+            "{}\n",
+            format!(
+                "{} run(s) without a reported cost are not in the Cost column",
+                unpriced
+            )
+            .dark_grey()
+        ));
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn colorize_empty_pattern() {
-        let result = colorize_regex("");
+        let result = colorize_regex("", Flavor::Rust);
         assert_eq!(result, "");
     }
 
     #[test]
     fn colorize_plain_text() {
-        let result = colorize_regex("abc");
+        let result = colorize_regex("abc", Flavor::Rust);
         assert_eq!(result, "abc");
     }
 
@@ -238,7 +622,7 @@ mod tests {
     fn colorize_character_classes_cyan() {
         // \d, \D, \w, \W, \s, \S should be cyan
         for class in &[r"\d", r"\D", r"\w", r"\W", r"\s", r"\S"] {
-            let result = colorize_regex(class);
+            let result = colorize_regex(class, Flavor::Rust);
             // Result should be longer (has ANSI escapes) and contain the class
             assert!(
                 result.len() > class.len(),
@@ -253,7 +637,7 @@ mod tests {
     fn colorize_escaped_anchors_red() {
         // \b, \B, \A, \z, \Z should be red
         for anchor in &[r"\b", r"\B", r"\A", r"\z", r"\Z"] {
-            let result = colorize_regex(anchor);
+            let result = colorize_regex(anchor, Flavor::Pcre);
             assert!(
                 result.len() > anchor.len(),
                 "Expected ANSI escapes for {}",
@@ -263,13 +647,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn colorize_leaves_anchors_the_flavor_lacks() {
+        assert_eq!(colorize_regex(r"\Z", Flavor::Rust), r"\Z");
+        assert_eq!(colorize_regex(r"\z", Flavor::Python), r"\z");
+    }
+
     #[test]
     fn colorize_line_anchors_red() {
         // ^ and $ should be red
-        let caret = colorize_regex("^");
+        let caret = colorize_regex("^", Flavor::Rust);
         assert!(caret.len() > 1, "Expected ANSI escapes for ^");
 
-        let dollar = colorize_regex("$");
+        let dollar = colorize_regex("$", Flavor::Rust);
         assert!(dollar.len() > 1, "Expected ANSI escapes for $");
     }
 
@@ -277,7 +667,7 @@ mod tests {
     fn colorize_quantifiers_yellow() {
         // +, *, ? should be yellow
         for q in &["+", "*", "?"] {
-            let result = colorize_regex(q);
+            let result = colorize_regex(q, Flavor::Rust);
             assert!(result.len() > 1, "Expected ANSI escapes for {}", q);
         }
     }
@@ -286,7 +676,7 @@ mod tests {
     fn colorize_quantifier_braces_yellow() {
         // {3}, {1,5}, {2,} should be yellow
         for q in &["{3}", "{1,5}", "{2,}"] {
-            let result = colorize_regex(q);
+            let result = colorize_regex(q, Flavor::Rust);
             assert!(result.len() > q.len(), "Expected ANSI escapes for {}", q);
             assert!(result.contains(q), "Expected {} in output", q);
         }
@@ -294,7 +684,7 @@ mod tests {
 
     #[test]
     fn colorize_alternation_yellow() {
-        let result = colorize_regex("|");
+        let result = colorize_regex("|", Flavor::Rust);
         assert!(result.len() > 1, "Expected ANSI escapes for |");
     }
 
@@ -302,7 +692,7 @@ mod tests {
     fn colorize_character_sets_magenta() {
         // [abc], [^abc], [a-z] should be magenta (entire set)
         for set in &["[abc]", "[^abc]", "[a-z]", "[0-9]"] {
-            let result = colorize_regex(set);
+            let result = colorize_regex(set, Flavor::Rust);
             assert!(
                 result.len() > set.len(),
                 "Expected ANSI escapes for {}",
@@ -315,7 +705,7 @@ mod tests {
     #[test]
     fn colorize_character_set_with_escapes() {
         // [\d\w] should be magenta as one unit
-        let result = colorize_regex(r"[\d\w]");
+        let result = colorize_regex(r"[\d\w]", Flavor::Rust);
         assert!(result.len() > 6, "Expected ANSI escapes for [\\d\\w]");
         assert!(result.contains(r"[\d\w]"), "Set should stay together");
     }
@@ -323,17 +713,17 @@ mod tests {
     #[test]
     fn colorize_groups_green() {
         // ( and ) should be green
-        let open = colorize_regex("(");
+        let open = colorize_regex("(", Flavor::Rust);
         assert!(open.len() > 1, "Expected ANSI escapes for (");
 
-        let close = colorize_regex(")");
+        let close = colorize_regex(")", Flavor::Rust);
         assert!(close.len() > 1, "Expected ANSI escapes for )");
     }
 
     #[test]
     fn colorize_non_capturing_group() {
         // (?:...) - parens green, content normal
-        let result = colorize_regex("(?:abc)");
+        let result = colorize_regex("(?:abc)", Flavor::Rust);
         assert!(result.contains("abc"), "Content should be present");
         assert!(result.len() > 7, "Expected ANSI escapes");
     }
@@ -341,7 +731,7 @@ mod tests {
     #[test]
     fn colorize_complex_pattern() {
         // ^\d{3}-\d{4}$ - mixed colors
-        let result = colorize_regex(r"^\d{3}-\d{4}$");
+        let result = colorize_regex(r"^\d{3}-\d{4}$", Flavor::Rust);
         // Should be much longer than input due to multiple color escapes
         assert!(result.len() > 15, "Expected many ANSI escapes");
         // Should contain the literal dash
@@ -351,7 +741,7 @@ mod tests {
     #[test]
     fn colorize_escaped_brackets_not_treated_as_set() {
         // \[ and \] should NOT trigger character set parsing
-        let result = colorize_regex(r"\[abc\]");
+        let result = colorize_regex(r"\[abc\]", Flavor::Rust);
         // This should just show the escapes normally, not as a magenta set
         assert!(result.contains(r"\["), "Escaped [ should be present");
         assert!(result.contains(r"\]"), "Escaped ] should be present");
@@ -360,7 +750,7 @@ mod tests {
     #[test]
     fn colorize_literal_close_bracket_first_in_set() {
         // []abc] - ] as first char is literal, set is []abc]
-        let result = colorize_regex("[]abc]");
+        let result = colorize_regex("[]abc]", Flavor::Rust);
         assert!(result.len() > 6, "Expected ANSI escapes");
         assert!(result.contains("[]abc]"), "Set should include literal ]");
     }
@@ -368,7 +758,7 @@ mod tests {
     #[test]
     fn colorize_negated_set_with_literal_bracket() {
         // [^]abc] - negated set with ] as first char after ^
-        let result = colorize_regex("[^]abc]");
+        let result = colorize_regex("[^]abc]", Flavor::Rust);
         assert!(result.len() > 7, "Expected ANSI escapes");
         assert!(result.contains("[^]abc]"), "Negated set should be intact");
     }
@@ -376,7 +766,7 @@ mod tests {
     #[test]
     fn colorize_unclosed_set_stays_plain() {
         // [abc without closing ] - should not colorize as set
-        let result = colorize_regex("[abc");
+        let result = colorize_regex("[abc", Flavor::Rust);
         // Without closing bracket, it just shows characters
         assert!(result.contains("["), "Opening bracket should be present");
     }
@@ -384,26 +774,301 @@ mod tests {
     #[test]
     fn colorize_unclosed_quantifier_brace() {
         // {3 without closing } - should not colorize as quantifier
-        let result = colorize_regex("{3");
+        let result = colorize_regex("{3", Flavor::Rust);
         assert!(result.contains("{"), "Opening brace should be present");
         assert!(result.contains("3"), "Number should be present");
     }
 
+    #[test]
+    fn colorize_posix_class_inside_set() {
+        let result = colorize_regex("[[:digit:]_]x", Flavor::Posix);
+        assert!(
+            result.contains(&"[[:digit:]_]".magenta().to_string()),
+            "POSIX class should not end the set"
+        );
+    }
+
+    #[test]
+    fn colorize_basic_syntax() {
+        let result = colorize_regex(r"\(a\)\{2,3\}", Flavor::Bre);
+        assert!(result.contains(&r"\(".green().to_string()));
+        assert!(result.contains(&r"\)".green().to_string()));
+        assert!(result.contains(&r"\{2,3\}".yellow().to_string()));
+        // Plain characters in basic syntax
+        assert_eq!(colorize_regex("(a+|b?){2}", Flavor::Bre), "(a+|b?){2}");
+        assert!(colorize_regex("a*", Flavor::Bre).contains(&"*".yellow().to_string()));
+    }
+
     #[test]
     fn colorize_other_escapes_not_colored() {
         // \n, \t, \. should pass through without special color
         for esc in &[r"\n", r"\t", r"\."] {
-            let result = colorize_regex(esc);
+            let result = colorize_regex(esc, Flavor::Rust);
             assert!(result.contains(esc), "Escape {} should pass through", esc);
         }
     }
 
+    fn make_metadata() -> Metadata {
+        Metadata {
+            backend: "claude".to_string(),
+            model: "sonnet".to_string(),
+            ..Metadata::default()
+        }
+    }
+
+    fn make_generation(pattern: &str, matches: &[&str], non_matches: &[&str]) -> Generation {
+        let response = GenerateResponse {
+            pattern: pattern.to_string(),
+            matches: matches.iter().map(|s| s.to_string()).collect(),
+            non_matches: non_matches.iter().map(|s| s.to_string()).collect(),
+            explanation: "digits".to_string(),
+        };
+        Generation {
+            verification: crate::verify::verify(&response, Flavor::Rust),
+            response,
+            flavor: Flavor::Rust,
+            metadata: make_metadata(),
+            attempts: 1,
+        }
+    }
+
+    #[test]
+    fn raw_generate_includes_model() {
+        let out = format_generate(&make_generation(r"\d+", &["1"], &["a"]), true);
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["pattern"], r"\d+");
+        assert_eq!(json["model"], "sonnet");
+        assert_eq!(json["backend"], "claude");
+        assert!(json.get("session_id").is_none());
+    }
+
+    #[test]
+    fn raw_generate_includes_verification() {
+        let out = format_generate(&make_generation(r"^\d+$", &["1", "x"], &["a"]), true);
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["verification"]["passed"], false);
+        assert_eq!(json["verification"]["matches"][0]["actual"], true);
+        assert_eq!(json["verification"]["matches"][1]["actual"], false);
+        assert_eq!(json["verification"]["non_matches"][0]["actual"], false);
+    }
+
+    #[test]
+    fn generate_marks_each_example() {
+        let out = format_generate(&make_generation(r"^\d+$", &["1", "x"], &["a", "2"]), false);
+        assert_eq!(out.matches('✓').count(), 2);
+        assert_eq!(out.matches('✗').count(), 2);
+        assert!(out.contains("(no match)"));
+        assert!(out.contains("(matches)"));
+    }
+
+    #[test]
+    fn generate_reports_uncompilable_pattern() {
+        let out = format_generate(&make_generation(r"(?<=a)b", &["ab"], &[]), false);
+        assert!(out.contains("Not verified:"));
+        assert!(!out.contains('✓'));
+        assert!(!out.contains('✗'));
+    }
+
+    #[test]
+    fn generate_reports_patterns_beyond_the_engine() {
+        let mut gen = make_generation(r"^[^]$", &["a"], &[]);
+        gen.flavor = Flavor::Js;
+        gen.verification = crate::verify::verify(&gen.response, Flavor::Js);
+        let out = format_generate(&gen, false);
+        assert!(out.contains("fancy-regex cannot run this pattern, though JavaScript"));
+
+        let json: serde_json::Value = serde_json::from_str(&format_generate(&gen, true)).unwrap();
+        assert_eq!(json["verification"]["unsupported"], true);
+    }
+
+    #[test]
+    fn generate_warns_about_unsupported_constructs() {
+        let gen = make_generation(r"(?<=a)b", &["ab"], &[]);
+        let out = format_generate(&gen, false);
+        assert!(out.contains("lookbehind (?<= is not supported by Rust regex crate"));
+
+        let json: serde_json::Value = serde_json::from_str(&format_generate(&gen, true)).unwrap();
+        assert_eq!(json["issues"][0]["construct"], "lookbehind");
+        assert_eq!(json["issues"][0]["end"], 4);
+        let clean = format_generate(&make_generation(r"\d+", &[], &[]), true);
+        assert!(!clean.contains("issues"));
+    }
+
+    #[test]
+    fn generate_prints_a_check_issue_once() {
+        let mut gen = make_generation(r"\p{L}+", &["a"], &[]);
+        gen.flavor = Flavor::Python;
+        gen.verification = crate::verify::verify(&gen.response, Flavor::Python);
+        let message = &check::check(r"\p{L}+", Flavor::Python)[0].message;
+        assert_eq!(gen.verification.error.as_ref(), Some(message));
+        let out = format_generate(&gen, false);
+        assert_eq!(out.matches(message.as_str()).count(), 1);
+        assert!(!out.contains("Verified with"));
+    }
+
+    #[test]
+    fn check_underlines_each_issue() {
+        let pattern = r"a\d(?:b)";
+        let issues = check::check(pattern, Flavor::Posix);
+        let out = format_check(pattern, Flavor::Posix, &issues, false);
+        assert!(out.contains("2 constructs unsupported by POSIX ERE"));
+        assert!(out.contains(&format!("\n   {}", "^^".red().bold())));
+        assert!(out.contains(&format!("\n     {}", "^^^".red().bold())));
+        assert!(out.contains("use [[:digit:]]"));
+        assert!(format_check("abc", Flavor::Posix, &[], false).contains("No constructs"));
+    }
+
+    #[test]
+    fn convert_lists_changes_and_lossiness() {
+        let conversion = crate::rewrite::convert(r"(?i)x+\Z", Flavor::Pcre, Flavor::Js);
+        let out = format_convert(&conversion, None, false);
+        assert!(out.contains("JavaScript (ECMAScript):"));
+        assert!(out.contains("(removed)"));
+        assert!(out.contains("/.../i"));
+        assert!(out.contains("Lossy:"));
+        assert!(!out.contains("Cannot translate"));
+    }
+
+    #[test]
+    fn convert_names_flags_moved_out_of_the_pattern() {
+        let conversion = crate::rewrite::convert("(?i)abc", Flavor::Pcre, Flavor::Js);
+        let out = format_convert(&conversion, None, false);
+        assert!(out.contains("only with the i flags set"));
+        assert!(out.contains("Exact translation with the i flags"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&format_convert(&conversion, None, true)).unwrap();
+        assert_eq!(json["flags"], "i");
+    }
+
+    #[test]
+    fn convert_reports_impossible_translation() {
+        let conversion = crate::rewrite::convert(r"(?<=a)\d", Flavor::Pcre, Flavor::Posix);
+        let out = format_convert(&conversion, None, false);
+        assert!(out.contains("Cannot translate to POSIX ERE:"));
+        assert!(out.contains("lookbehind (?<= is not supported"));
+        assert!(out.contains("Partial translation:"));
+        assert!(!out.contains("Exact translation"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&format_convert(&conversion, Some(&make_metadata()), true))
+                .unwrap();
+        assert_eq!(json["possible"], false);
+        assert_eq!(json["pattern"], r"(?<=a)[[:digit:]]");
+        assert_eq!(json["remaining"][0]["construct"], "lookbehind");
+        assert_eq!(json["model"], "sonnet");
+    }
+
+    #[test]
+    fn raw_check_reports_compatibility() {
+        let issues = check::check(r"\Ax", Flavor::Js);
+        let json: serde_json::Value =
+            serde_json::from_str(&format_check(r"\Ax", Flavor::Js, &issues, true)).unwrap();
+        assert_eq!(json["flavor"], "js");
+        assert_eq!(json["compatible"], false);
+        assert_eq!(json["issues"][0]["text"], r"\A");
+        assert_eq!(json["issues"][0]["start"], 0);
+    }
+
+    #[test]
+    fn raw_explain_includes_model() {
+        let resp = ExplainResponse {
+            tokens: vec![],
+            purpose: "nothing".to_string(),
+        };
+        let out = format_explain(&resp, &make_metadata(), Flavor::Rust, true);
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["purpose"], "nothing");
+        assert_eq!(json["model"], "sonnet");
+    }
+
+    #[test]
+    fn colorize_skips_escapes_the_flavor_lacks() {
+        assert_eq!(colorize_regex(r"\d\b\A", Flavor::Posix), r"\d\b\A");
+        assert_eq!(colorize_regex(r"\A", Flavor::Js), r"\A");
+        assert_ne!(colorize_regex(r"\d", Flavor::Js), r"\d");
+    }
+
+    #[test]
+    fn colorize_unicode_classes() {
+        let result = colorize_regex(r"\p{Greek}+", Flavor::Rust);
+        assert!(result.contains(&r"\p{Greek}".cyan().to_string()));
+        assert!(result.contains(&"+".yellow().to_string()));
+        assert!(colorize_regex(r"\pL", Flavor::Pcre).contains(&r"\pL".cyan().to_string()));
+        assert!(!colorize_regex(r"\p{L}", Flavor::Posix).contains(&r"\p{L}".cyan().to_string()));
+    }
+
     #[test]
     fn colorize_email_like_pattern() {
         // Realistic pattern: [a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}
         let pattern = r"[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}";
-        let result = colorize_regex(pattern);
+        let result = colorize_regex(pattern, Flavor::Rust);
         assert!(result.len() > pattern.len(), "Expected ANSI escapes");
         assert!(result.contains("@"), "Literal @ should be present");
     }
+
+    #[test]
+    fn usage_line_reports_tokens_cost_and_time() {
+        let metadata = Metadata {
+            usage: Some(crate::backend::Usage {
+                input_tokens: 1200,
+                output_tokens: 45,
+                cost_usd: Some(0.01234),
+                duration_ms: 3400,
+            }),
+            ..make_metadata()
+        };
+        let out = format_usage(&metadata);
+        assert!(out.contains("1200 tokens in, 45 out, cost $0.0123, 3.4s"));
+    }
+
+    #[test]
+    fn usage_line_without_usage() {
+        assert!(format_usage(&make_metadata()).contains("no usage reported by claude"));
+        let cached = Metadata {
+            cached: true,
+            ..make_metadata()
+        };
+        assert!(format_usage(&cached).contains("served from cache"));
+    }
+
+    #[test]
+    fn ledger_table_and_raw() {
+        let months = vec![MonthSummary {
+            month: "2026-10".to_string(),
+            runs: 3,
+            input_tokens: 1500,
+            output_tokens: 90,
+            cost_usd: 0.042,
+            unpriced: 0,
+            duration_ms: 9000,
+        }];
+        let table = format_ledger(&months, false);
+        assert!(table.contains("2026-10"));
+        assert!(table.contains("$0.04 "));
+        assert!(table.contains("9s"));
+        assert!(!table.contains("without a reported cost"));
+
+        let json: serde_json::Value = serde_json::from_str(&format_ledger(&months, true)).unwrap();
+        assert_eq!(json[0]["runs"], 3);
+        assert_eq!(format_ledger(&[], false), "No usage recorded yet");
+    }
+
+    #[test]
+    fn ledger_marks_runs_without_cost() {
+        let month = |runs, unpriced, cost_usd| MonthSummary {
+            month: "2026-10".to_string(),
+            runs,
+            input_tokens: 10,
+            output_tokens: 1,
+            cost_usd,
+            unpriced,
+            duration_ms: 1000,
+        };
+        let table = format_ledger(&[month(2, 2, 0.0)], false);
+        assert!(table.contains("unknown"));
+        assert!(!table.contains("$0.00"));
+        assert!(table.contains("2 run(s) without a reported cost"));
+        assert!(format_ledger(&[month(3, 1, 0.5)], false).contains("$0.50+"));
+    }
 }
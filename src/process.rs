@@ -0,0 +1,174 @@
+use crate::error::{Error, Result};
+use crate::log;
+use crate::spinner;
+use std::io::Read;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// Children currently being waited on by `output`
+static WAITING: AtomicUsize = AtomicUsize::new(0);
+
+/// Route Ctrl-C through rgx: a running child is killed and its caller gets
+/// `Error::Interrupted`; with nothing running, rgx exits as it would have
+/// without the handler.
+pub fn install_interrupt_handler() {
+    let _ = ctrlc::set_handler(|| {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+        if WAITING.load(Ordering::SeqCst) == 0 {
+            spinner::clear();
+            std::process::exit(130);
+        }
+    });
+}
+
+/// Marks a child as waited on for as long as it lives.
+struct Waiting;
+
+impl Waiting {
+    fn new() -> Self {
+        WAITING.fetch_add(1, Ordering::SeqCst);
+        Waiting
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        WAITING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Run `command` to completion like `Command::output`, but kill it and
+/// return `Error::Timeout` once `timeout` passes, or `Error::Interrupted`
+/// on Ctrl-C.
+pub fn output(command: &mut Command, timeout: Duration) -> Result<Output> {
+    log::debug(format_args!("running {}", command_line(command)));
+    let _waiting = Waiting::new();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Drain both pipes as the child writes so it never blocks on a full one
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        let error = if INTERRUPTED.load(Ordering::SeqCst) {
+            Error::Interrupted
+        } else if Instant::now() >= deadline {
+            Error::Timeout(timeout)
+        } else {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        };
+        kill(&mut child);
+        return Err(error);
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// `command` as it could be pasted into a POSIX shell.
+pub fn command_line(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| quote(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@,+".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+    // Reap it so no zombie is left behind
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captures_output_and_status() {
+        let output = output(
+            Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]),
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+        assert_eq!(output.status.code(), Some(3));
+    }
+
+    #[test]
+    fn large_output_does_not_block() {
+        let output = output(
+            Command::new("sh").args(["-c", "head -c 1000000 /dev/zero"]),
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        assert_eq!(output.stdout.len(), 1_000_000);
+    }
+
+    #[test]
+    fn hung_child_times_out() {
+        let start = Instant::now();
+        let err = output(Command::new("sleep").arg("30"), Duration::from_millis(200)).unwrap_err();
+        assert!(matches!(err, Error::Timeout(_)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn command_line_quotes_for_the_shell() {
+        let mut command = Command::new("claude");
+        command.args(["-p", "it's a \\d+ test", "--model", "haiku", ""]);
+        assert_eq!(
+            command_line(&command),
+            r"claude -p 'it'\''s a \d+ test' --model haiku ''"
+        );
+    }
+
+    #[test]
+    fn missing_program_is_io_error() {
+        let err = output(
+            &mut Command::new("rgx-no-such-program"),
+            Duration::from_secs(1),
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+    }
+}
@@ -0,0 +1,880 @@
+//! Local translation of a pattern between flavors.
+//!
+//! Each construct the target flavor lacks is rewritten in place when it
+//! has a mechanical equivalent: named-group spelling, shorthand and POSIX
+//! classes, string anchors, leading flag groups. Whatever is left, such as
+//! lookaround, needs a semantic rewrite and is reported for the model.
+//!
+//! POSIX basic patterns are read and written through their extended form,
+//! so the rewriting itself only deals with one syntax. POSIX also reads a
+//! backslash inside brackets as an ordinary member, so bracket expressions
+//! are re-escaped whenever exactly one side is POSIX.
+//!
+//! The source must be a valid pattern for its own flavor; one that is not
+//! has no meaning to translate.
+
+use crate::check::{self, Construct, Issue, Token};
+use crate::engine::Engine;
+use crate::error::Error;
+use crate::flavor::Flavor;
+use crate::syntax::{basic_to_extended, bracket_end, extended_to_basic};
+use serde::Serialize;
+use std::ops::Range;
+
+/// One construct rewritten for the target flavor.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The construct as written in the source pattern
+    pub text: String,
+    pub replacement: String,
+    /// Whether the rewrite behaves differently on some inputs
+    pub lossy: bool,
+    /// What to know about the rewrite, if anything
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// A pattern translated from one flavor to another.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    pub from: Flavor,
+    pub to: Flavor,
+    pub source: String,
+    /// The translated pattern, as far as translation got
+    pub pattern: String,
+    pub changes: Vec<Change>,
+    /// Flags the translation only matches like the source with, moved out
+    /// of a leading `(?flags)` group, to be set where it is compiled
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub flags: String,
+    /// Explanations from the model, when it was asked to finish the job
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Whether the translation matches differently from the source on some
+    /// inputs
+    pub lossy: bool,
+    /// Whether `pattern` is a working `to` pattern
+    pub possible: bool,
+    /// Constructs in `pattern` that `to` still does not support
+    pub remaining: Vec<Issue>,
+}
+
+impl Conversion {
+    /// Take `pattern` as the translation and re-check it against the
+    /// target. A pattern that still uses unsupported constructs or does not
+    /// compile is not a possible translation.
+    pub fn settle(&mut self, pattern: String) {
+        self.remaining = check::check(&pattern, self.to);
+        self.possible = self.remaining.is_empty();
+        if self.possible {
+            match Engine::compile(&pattern, self.to) {
+                Err(Error::InvalidRegex { engine, message }) => {
+                    self.possible = false;
+                    self.notes.push(format!(
+                        "the result does not compile with {}: {}",
+                        engine, message
+                    ));
+                }
+                Err(Error::UnsupportedRegex { engine, .. }) => self.notes.push(format!(
+                    "the result could not be checked, since {} cannot run it",
+                    engine
+                )),
+                _ => {}
+            }
+        }
+        self.pattern = pattern;
+    }
+}
+
+/// Translate `pattern` from `from` to `to` as far as local rewriting can.
+pub fn convert(source: &str, from: Flavor, to: Flavor) -> Conversion {
+    let caps = to.capabilities();
+    let mut conversion = Conversion {
+        from,
+        to,
+        source: source.to_string(),
+        pattern: source.to_string(),
+        changes: Vec::new(),
+        flags: String::new(),
+        notes: Vec::new(),
+        lossy: false,
+        possible: false,
+        remaining: Vec::new(),
+    };
+    let invalid = check::check(source, from);
+    if !invalid.is_empty() {
+        conversion.notes.extend(
+            invalid
+                .iter()
+                .map(|issue| format!("the source is not a valid pattern: {}", issue.message)),
+        );
+        return conversion;
+    }
+    if let Err(Error::InvalidRegex { engine, message }) = Engine::compile(source, from) {
+        conversion.notes.push(format!(
+            "the source does not compile with {}: {}",
+            engine, message
+        ));
+        return conversion;
+    }
+
+    let from_caps = from.capabilities();
+    let from_basic = from_caps.basic_syntax;
+    let mut pattern = if from_basic {
+        match basic_to_extended(source) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                conversion.notes.push(e);
+                return conversion;
+            }
+        }
+    } else {
+        source.to_string()
+    };
+    let transliterated = pattern != source;
+
+    let mut changes = Vec::new();
+    if !from_caps.bracket_escapes && caps.bracket_escapes {
+        let escaped = escape_brackets(&pattern);
+        if escaped != pattern {
+            changes.push(Change {
+                text: r"\ [".to_string(),
+                replacement: r"\\ \[".to_string(),
+                lossy: false,
+                note: Some(format!(
+                    "inside brackets these are ordinary characters in {} and are escaped",
+                    from.label()
+                )),
+            });
+            pattern = escaped;
+        }
+    }
+
+    let tokens = check::scan(&pattern, from_caps);
+    let mut edits = Vec::new();
+    let mut left = Vec::new();
+    for token in &tokens {
+        let text = &pattern[token.span.clone()];
+        // Python's \Z is everyone else's \z
+        let reread = token.construct == Construct::StringAnchor
+            && newline_end(text, from) != newline_end(text, to);
+        if token.construct.supported(text, caps) && !reread {
+            continue;
+        }
+        match rewrite(token, text, from, to) {
+            Some(change) => {
+                if token.construct == Construct::InlineFlags {
+                    // Flags turned off with (?-x) are off by default anyway
+                    let flags = &text[2..text.len() - 1];
+                    conversion.flags = flags.split('-').next().unwrap_or_default().to_string();
+                }
+                edits.push((token.span.clone(), change))
+            }
+            // The target's own check cannot see what it reads differently:
+            // escapes inside POSIX brackets, or group syntax in basic syntax
+            None if (token.in_class && !caps.bracket_escapes)
+                || (caps.basic_syntax && !text.starts_with('\\')) =>
+            {
+                left.push((token.construct, text.to_string()))
+            }
+            None => {}
+        }
+    }
+    edits.extend(renumber(&pattern, &tokens, &edits, from));
+    edits.sort_by_key(|(span, _)| span.start);
+
+    let mut rewritten = String::with_capacity(pattern.len());
+    let mut last = 0;
+    for (span, change) in edits {
+        rewritten.push_str(&pattern[last..span.start]);
+        rewritten.push_str(&change.replacement);
+        last = span.end;
+        changes.push(change);
+    }
+    rewritten.push_str(&pattern[last..]);
+
+    if from_caps.bracket_escapes && !caps.bracket_escapes {
+        let unescaped = unescape_brackets(&rewritten);
+        if unescaped != rewritten {
+            changes.push(Change {
+                text: r"\] \- \.".to_string(),
+                replacement: "] - .".to_string(),
+                lossy: false,
+                note: Some(format!(
+                    "a backslash inside brackets is an ordinary character in {}, so escaped members are written plainly, ] first and - last",
+                    to.label()
+                )),
+            });
+            rewritten = unescaped;
+        }
+    }
+
+    match (from_basic, caps.basic_syntax) {
+        (true, false) if transliterated => changes.insert(
+            0,
+            Change {
+                text: r"\( \) \{ \}".to_string(),
+                replacement: "( ) { }".to_string(),
+                lossy: false,
+                note: Some(
+                    "basic syntax rewritten as extended; ( ) { } + ? | are escaped where they were ordinary characters"
+                        .to_string(),
+                ),
+            },
+        ),
+        (false, true) => {
+            let basic = extended_to_basic(&rewritten);
+            if basic != rewritten {
+                changes.push(Change {
+                    text: "( ) { } + ?".to_string(),
+                    replacement: r"\( \) \{ \} \{1,\} \{0,1\}".to_string(),
+                    lossy: false,
+                    note: Some("extended syntax rewritten as basic".to_string()),
+                });
+            }
+            rewritten = basic;
+        }
+        (true, true) => rewritten = extended_to_basic(&rewritten),
+        _ => {}
+    }
+
+    conversion.lossy = changes.iter().any(|change| change.lossy);
+    conversion.changes = changes;
+    conversion.settle(rewritten);
+
+    let mut cursor = 0;
+    for (construct, text) in left {
+        let basic = extended_to_basic(&text);
+        let (start, text) = [text.as_str(), basic.as_str()]
+            .into_iter()
+            .find_map(|text| {
+                let start = conversion.pattern.get(cursor..)?.find(text)? + cursor;
+                Some((start, text))
+            })
+            .unwrap_or((cursor, text.as_str()));
+        cursor = start + text.len();
+        conversion
+            .remaining
+            .push(check::issue(construct, text, start, to));
+        conversion.possible = false;
+    }
+    conversion.remaining.sort_by_key(|issue| issue.start);
+    conversion
+}
+
+/// Edits renumbering the backreferences in `pattern` after `edits` turn
+/// non-capturing groups into capturing ones, which shifts every later group
+/// up by one.
+fn renumber(
+    pattern: &str,
+    tokens: &[Token],
+    edits: &[(Range<usize>, Change)],
+    from: Flavor,
+) -> Vec<(Range<usize>, Change)> {
+    let opened: Vec<usize> = edits
+        .iter()
+        .filter(|(span, change)| {
+            change.replacement == "("
+                && pattern[span.clone()].starts_with("(?")
+                && !matches!(pattern.as_bytes()[span.start + 2], b'<' | b'P' | b'\'')
+        })
+        .map(|(span, _)| span.start)
+        .collect();
+    if opened.is_empty() {
+        return Vec::new();
+    }
+    let groups = capture_offsets(pattern, from);
+
+    tokens
+        .iter()
+        .filter(|token| token.construct == Construct::Backreference)
+        .filter_map(|token| {
+            let text = &pattern[token.span.clone()];
+            let number: usize = text.strip_prefix('\\')?.parse().ok()?;
+            let group = *groups.get(number.checked_sub(1)?)?;
+            let shift = opened.iter().filter(|&&at| at < group).count();
+            (shift > 0).then(|| {
+                (
+                    token.span.clone(),
+                    Change {
+                        text: text.to_string(),
+                        replacement: format!("\\{}", number + shift),
+                        lossy: false,
+                        note: Some("renumbered for the groups that now capture".to_string()),
+                    },
+                )
+            })
+        })
+        .collect()
+}
+
+/// Byte offset of every capturing group in `pattern`, in group-number order.
+fn capture_offsets(pattern: &str, from: Flavor) -> Vec<usize> {
+    let bracket_escapes = from.capabilities().bracket_escapes;
+    let bytes = pattern.as_bytes();
+    let mut groups = Vec::new();
+    let mut in_class = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if bracket_escapes || !in_class => i += 1,
+            b'[' if in_class && matches!(bytes.get(i + 1), Some(b':' | b'=' | b'.')) => {
+                let delimiter = bytes[i + 1];
+                while i + 1 < bytes.len() && !(bytes[i] == delimiter && bytes[i + 1] == b']') {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'[' if !in_class => {
+                in_class = true;
+                if bytes.get(i + 1) == Some(&b'^') {
+                    i += 1;
+                }
+                if bytes.get(i + 1) == Some(&b']') {
+                    i += 1;
+                }
+            }
+            b']' => in_class = false,
+            b'(' if !in_class => {
+                let rest = &pattern[i + 1..];
+                let named = rest.starts_with("?P<")
+                    || rest.starts_with("?'")
+                    || (rest.starts_with("?<")
+                        && !rest.starts_with("?<=")
+                        && !rest.starts_with("?<!"));
+                if !rest.starts_with('?') || named {
+                    groups.push(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    groups
+}
+
+/// `pattern`, read with POSIX brackets, with every bracket expression
+/// escaped for a flavor where a backslash inside brackets escapes. `\`, `[`
+/// and a literal `-` are escaped, and so are doubled `&` and `~`, which
+/// some flavors read as set operations.
+fn escape_brackets(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::with_capacity(pattern.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' {
+            out.push(c);
+            if let Some(&next) = chars.get(i + 1) {
+                out.push(next);
+            }
+            i += 2;
+            continue;
+        }
+        let Some(end) = (c == '[').then(|| bracket_end(&chars, i)).flatten() else {
+            out.push(c);
+            i += 1;
+            continue;
+        };
+        out.push('[');
+        let mut j = i + 1;
+        if chars[j] == '^' {
+            out.push('^');
+            j += 1;
+        }
+        if chars[j] == ']' {
+            out.push_str(r"\]");
+            j += 1;
+        }
+        let first = j;
+        while j < end {
+            let member = chars[j];
+            if member == '[' && matches!(chars.get(j + 1), Some(':' | '=' | '.')) {
+                let close = (j + 2..end)
+                    .find(|&k| chars[k] == chars[j + 1] && chars[k + 1] == ']')
+                    .unwrap_or(end - 1);
+                out.extend(&chars[j..close + 2]);
+                j = close + 2;
+                continue;
+            }
+            // The - of a range stays as it is; a literal - is escaped
+            let range = member == '-' && j > first && j + 1 < end;
+            let doubled = chars[j + 1] == member || chars[j - 1] == member;
+            if matches!(member, '\\' | '[')
+                || (member == '-' && !range)
+                || (matches!(member, '&' | '~') && doubled)
+            {
+                out.push('\\');
+            }
+            out.push(member);
+            j += 1;
+        }
+        out.push(']');
+        i = end + 1;
+    }
+    out
+}
+
+/// `pattern` with every bracket expression rewritten for POSIX, where a
+/// backslash inside brackets is an ordinary member. Escaped punctuation is
+/// written plainly, with `]` moved first and `-` last; escaped letters are
+/// left, as they have no POSIX spelling.
+fn unescape_brackets(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::with_capacity(pattern.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                out.extend(&chars[i..(i + 2).min(chars.len())]);
+                i += 2;
+            }
+            '[' => match unescape_bracket(&chars, i) {
+                Some((bracket, end)) => {
+                    out.push_str(&bracket);
+                    i = end + 1;
+                }
+                None => {
+                    out.extend(&chars[i..]);
+                    i = chars.len();
+                }
+            },
+            c => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// The bracket expression opening at `start`, read with escapes, written
+/// for POSIX, and the index of its `]`.
+fn unescape_bracket(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut i = start + 1;
+    let negated = chars.get(i) == Some(&'^');
+    if negated {
+        i += 1;
+    }
+    let mut close = false;
+    if chars.get(i) == Some(&']') {
+        close = true;
+        i += 1;
+    }
+    let (mut dash, mut caret) = (false, false);
+    let mut body = String::new();
+    loop {
+        match *chars.get(i)? {
+            ']' => break,
+            '\\' => {
+                match *chars.get(i + 1)? {
+                    ']' => close = true,
+                    '-' => dash = true,
+                    '^' => caret = true,
+                    c if c.is_ascii_alphanumeric() => {
+                        body.push('\\');
+                        body.push(c);
+                    }
+                    c => body.push(c),
+                }
+                i += 2;
+            }
+            '[' if matches!(chars.get(i + 1), Some(':' | '=' | '.')) => {
+                let delimiter = chars[i + 1];
+                let end = (i + 2..chars.len().saturating_sub(1))
+                    .find(|&k| chars[k] == delimiter && chars[k + 1] == ']')?;
+                body.extend(&chars[i..end + 2]);
+                i = end + 2;
+            }
+            c => {
+                body.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    if !negated && !close && body.is_empty() && !dash && caret {
+        return Some((r"\^".to_string(), i));
+    }
+    let mut bracket = String::from("[");
+    if negated {
+        bracket.push('^');
+    }
+    if close {
+        bracket.push(']');
+    }
+    bracket.push_str(&body);
+    if caret {
+        bracket.push('^');
+    }
+    if dash {
+        bracket.push('-');
+    }
+    bracket.push(']');
+    Some((bracket, i))
+}
+
+/// Whether the end anchor `text` also matches before a final newline in
+/// `flavor`: `\Z` does, except in Python, where it is the very end.
+fn newline_end(text: &str, flavor: Flavor) -> bool {
+    text == r"\Z" && flavor.capabilities().end_anchor != Some(r"\Z")
+}
+
+/// Whether `$` outside multi-line mode also matches before a final newline.
+fn dollar_before_newline(flavor: Flavor) -> bool {
+    matches!(
+        flavor,
+        Flavor::Pcre | Flavor::Python | Flavor::Java | Flavor::Dotnet
+    )
+}
+
+/// The local rewrite of an unsupported `token`, if it has one.
+fn rewrite(token: &Token, text: &str, from: Flavor, to: Flavor) -> Option<Change> {
+    let caps = to.capabilities();
+    let exact = |replacement: &str| (replacement.to_string(), false, None);
+    let (replacement, lossy, note) = match token.construct {
+        Construct::NamedGroup => {
+            let name = text
+                .trim_start_matches("(?P<")
+                .trim_start_matches("(?<")
+                .trim_start_matches("(?'")
+                .trim_end_matches(['>', '\'']);
+            match caps.named_groups {
+                [] => (
+                    "(".to_string(),
+                    false,
+                    Some(format!(
+                        "group name {} dropped; refer to it by number",
+                        name
+                    )),
+                ),
+                [preferred, ..] => exact(&preferred.replace("name", name).replace("...)", "")),
+            }
+        }
+        Construct::Backreference if caps.backreferences && !text.starts_with('\\') => {
+            let name = &text[4..text.len() - 1];
+            exact(&format!("\\k<{}>", name))
+        }
+        Construct::Backreference if caps.backreferences => {
+            let name = &text[3..text.len() - 1];
+            exact(&format!("(?P={})", name))
+        }
+        Construct::ShorthandClass if caps.posix_classes => {
+            let (class, negated) = match &text[1..] {
+                "d" => ("[:digit:]", false),
+                "D" => ("[:digit:]", true),
+                "w" => ("[:alnum:]_", false),
+                "W" => ("[:alnum:]_", true),
+                "s" => ("[:space:]", false),
+                _ => ("[:space:]", true),
+            };
+            let replacement = match (token.in_class, negated) {
+                (true, true) => return None,
+                (true, false) => class.to_string(),
+                (false, negated) => format!("[{}{}]", if negated { "^" } else { "" }, class),
+            };
+            // These flavors' shorthand classes are Unicode-aware by default,
+            // POSIX classes follow the locale
+            let unicode = matches!(from, Flavor::Rust | Flavor::Python | Flavor::Dotnet);
+            (
+                replacement,
+                unicode,
+                unicode.then(|| {
+                    format!(
+                        "{} also matches non-ASCII characters in {}",
+                        text,
+                        from.label()
+                    )
+                }),
+            )
+        }
+        Construct::PosixClass => exact(match &text[2..text.len() - 2] {
+            "digit" => "0-9",
+            "alpha" => "a-zA-Z",
+            "alnum" => "a-zA-Z0-9",
+            "upper" => "A-Z",
+            "lower" => "a-z",
+            "xdigit" => "0-9A-Fa-f",
+            "space" => r" \t\n\r\f\v",
+            "blank" => r" \t",
+            "punct" => r"!-\/:-@\[-`{-~",
+            "cntrl" => r"\x00-\x1f\x7f",
+            "print" => r" -~",
+            "graph" => r"!-~",
+            _ => return None,
+        }),
+        Construct::StringAnchor => match (text, caps.end_anchor) {
+            (r"\A", _) => (
+                "^".to_string(),
+                false,
+                Some("same as \\A while multi-line mode is off".to_string()),
+            ),
+            _ if newline_end(text, from) && caps.newline_end_anchor => exact(r"\Z"),
+            _ if newline_end(text, from) && dollar_before_newline(to) => (
+                "$".to_string(),
+                false,
+                Some(format!("same as {} while multi-line mode is off", text)),
+            ),
+            _ if newline_end(text, from) => (
+                "$".to_string(),
+                true,
+                Some("no longer matches before a trailing newline".to_string()),
+            ),
+            (_, Some(end)) => exact(end),
+            (_, None) => (
+                "$".to_string(),
+                false,
+                Some(format!("same as {} while multi-line mode is off", text)),
+            ),
+        },
+        // Only a flag group that opens the pattern applies to all of it
+        Construct::InlineFlags if token.span.start == 0 && text.ends_with(')') => (
+            String::new(),
+            false,
+            Some(format!(
+                "set the {flags} flags where the pattern is compiled instead, e.g. /.../{flags}",
+                flags = &text[2..text.len() - 1]
+            )),
+        ),
+        Construct::NonCapturingGroup => (
+            "(".to_string(),
+            false,
+            Some("now captures, so later groups and backreferences are renumbered".to_string()),
+        ),
+        Construct::AtomicGroup => (
+            if caps.non_capturing_groups {
+                "(?:"
+            } else {
+                "("
+            }
+            .to_string(),
+            true,
+            Some("backtracks into the group, so it can match more".to_string()),
+        ),
+        Construct::LazyQuantifier => (
+            text[..text.len() - 1].to_string(),
+            true,
+            Some(
+                "greedy instead of lazy: the same strings match, but the match found can be longer"
+                    .to_string(),
+            ),
+        ),
+        Construct::PossessiveQuantifier => (
+            text[..text.len() - 1].to_string(),
+            true,
+            Some("greedy instead of possessive: backtracks, so it can match more".to_string()),
+        ),
+        Construct::LetterEscape => match text {
+            r"\t" => exact("\t"),
+            r"\n" => exact("\n"),
+            r"\r" => exact("\r"),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(Change {
+        text: text.to_string(),
+        replacement,
+        lossy,
+        note,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replacements(conversion: &Conversion) -> Vec<(&str, &str)> {
+        conversion
+            .changes
+            .iter()
+            .map(|change| (change.text.as_str(), change.replacement.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn named_groups_take_the_target_spelling() {
+        let conversion = convert(r"(?P<year>\d{4})-(?P=year)", Flavor::Pcre, Flavor::Js);
+        assert_eq!(conversion.pattern, r"(?<year>\d{4})-\k<year>");
+        assert!(conversion.possible);
+        assert!(!conversion.lossy);
+        assert_eq!(
+            convert("(?'id'x)", Flavor::Pcre, Flavor::Rust).pattern,
+            "(?<id>x)"
+        );
+    }
+
+    #[test]
+    fn supported_constructs_are_left_alone() {
+        let conversion = convert(r"(?P<id>\d+)", Flavor::Pcre, Flavor::Rust);
+        assert_eq!(conversion.pattern, r"(?P<id>\d+)");
+        assert!(conversion.changes.is_empty());
+        assert!(conversion.possible);
+    }
+
+    #[test]
+    fn shorthand_classes_become_posix_classes() {
+        let conversion = convert(r"^\d+[\w.]\S$", Flavor::Pcre, Flavor::Posix);
+        assert_eq!(
+            conversion.pattern,
+            "^[[:digit:]]+[[:alnum:]_.][^[:space:]]$"
+        );
+        assert!(conversion.possible);
+        assert!(!conversion.lossy);
+        assert!(convert(r"\d", Flavor::Rust, Flavor::Posix).lossy);
+    }
+
+    #[test]
+    fn negated_shorthand_inside_brackets_is_left() {
+        let conversion = convert(r"x[\Da]", Flavor::Pcre, Flavor::Posix);
+        assert!(!conversion.possible);
+        assert_eq!(conversion.remaining[0].text, r"\D");
+        assert_eq!(conversion.remaining[0].start, 2);
+    }
+
+    #[test]
+    fn source_must_be_valid_for_its_flavor() {
+        let conversion = convert("(?<=a)b", Flavor::Posix, Flavor::Pcre);
+        assert!(!conversion.possible);
+        assert!(conversion.notes[0].contains("lookbehind (?<= is not supported by POSIX ERE"));
+        let conversion = convert("(a", Flavor::Pcre, Flavor::Js);
+        assert!(!conversion.possible);
+        assert!(conversion.notes[0].contains("does not compile with fancy-regex"));
+    }
+
+    #[test]
+    fn posix_brackets_are_escaped_on_the_way_out() {
+        let conversion = convert(r"^[\d]+$", Flavor::Posix, Flavor::Pcre);
+        assert_eq!(conversion.pattern, r"^[\\d]+$");
+        assert!(conversion.possible);
+        assert!(!conversion.lossy);
+        assert_eq!(
+            convert("[a[b&&c!--]", Flavor::Posix, Flavor::Rust).pattern,
+            r"[a\[b\&\&c!-\-]"
+        );
+        assert_eq!(
+            convert("[[:digit:]x]", Flavor::Posix, Flavor::Rust).pattern,
+            "[[:digit:]x]"
+        );
+    }
+
+    #[test]
+    fn escaped_bracket_members_are_written_plainly_for_posix() {
+        let conversion = convert(r"[a\]\-\.\\]", Flavor::Pcre, Flavor::Posix);
+        assert_eq!(conversion.pattern, r"[]a.\-]");
+        assert!(conversion.possible);
+        assert_eq!(convert(r"[\^]", Flavor::Js, Flavor::Posix).pattern, r"\^");
+    }
+
+    #[test]
+    fn group_syntax_left_in_basic_is_reported() {
+        let conversion = convert("(?=a)b", Flavor::Pcre, Flavor::Bre);
+        assert!(!conversion.possible);
+        assert_eq!(conversion.remaining[0].construct, Construct::Lookahead);
+    }
+
+    #[test]
+    fn posix_classes_become_ranges() {
+        let conversion = convert("^[[:alpha:][:digit:]_]+$", Flavor::Posix, Flavor::Js);
+        assert_eq!(conversion.pattern, "^[a-zA-Z0-9_]+$");
+        assert!(conversion.possible);
+    }
+
+    #[test]
+    fn string_anchors_become_line_anchors() {
+        let conversion = convert(r"\Aabc\z", Flavor::Rust, Flavor::Js);
+        assert_eq!(conversion.pattern, "^abc$");
+        assert!(!conversion.lossy);
+        let conversion = convert(r"\Aabc\Z", Flavor::Pcre, Flavor::Js);
+        assert!(conversion.lossy);
+        assert_eq!(replacements(&conversion)[1], (r"\Z", "$"));
+    }
+
+    #[test]
+    fn end_anchors_keep_their_meaning() {
+        let exact = |source: &str, from: Flavor, to: Flavor| {
+            let conversion = convert(source, from, to);
+            assert!(conversion.possible && !conversion.lossy, "{:?}", conversion);
+            conversion.pattern
+        };
+        assert_eq!(exact(r"a\z", Flavor::Pcre, Flavor::Python), r"a\Z");
+        assert_eq!(exact(r"a\Z", Flavor::Python, Flavor::Pcre), r"a\z");
+        assert_eq!(exact(r"a\Z", Flavor::Python, Flavor::Rust), r"a\z");
+        assert_eq!(exact(r"a\Z", Flavor::Pcre, Flavor::Python), "a$");
+        assert_eq!(exact(r"a\Z", Flavor::Pcre, Flavor::Java), r"a\Z");
+
+        let conversion = convert(r"a\Z", Flavor::Pcre, Flavor::Rust);
+        assert!(conversion.possible);
+        assert!(conversion.lossy);
+        assert_eq!(conversion.pattern, "a$");
+    }
+
+    #[test]
+    fn leading_flags_move_out_of_the_pattern() {
+        let conversion = convert("(?i)abc", Flavor::Pcre, Flavor::Js);
+        assert_eq!(conversion.pattern, "abc");
+        assert_eq!(conversion.flags, "i");
+        assert_eq!(convert("(?im-s)a", Flavor::Pcre, Flavor::Js).flags, "im");
+        assert!(conversion.changes[0]
+            .note
+            .as_ref()
+            .unwrap()
+            .contains("/.../i"));
+        assert!(!convert("a(?i)bc", Flavor::Pcre, Flavor::Js).possible);
+    }
+
+    #[test]
+    fn quantifier_suffixes_are_dropped_as_lossy() {
+        let conversion = convert(r"a+?(?:b)*+(?>c)", Flavor::Pcre, Flavor::Posix);
+        assert_eq!(conversion.pattern, "a+(b)*(c)");
+        assert!(conversion.lossy);
+        assert!(conversion.possible);
+        assert_eq!(
+            convert("(?>c)", Flavor::Pcre, Flavor::Rust).pattern,
+            "(?:c)"
+        );
+    }
+
+    #[test]
+    fn backreferences_follow_groups_that_now_capture() {
+        let conversion = convert(r"(?:a)(b)\1", Flavor::Pcre, Flavor::Bre);
+        assert_eq!(conversion.pattern, r"\(a\)\(b\)\2");
+        assert!(conversion.possible);
+        assert!(!conversion.lossy);
+        assert_eq!(
+            convert(r"(x)(?:a)(?>c)(b)\1\2", Flavor::Pcre, Flavor::Posix).pattern,
+            r"(x)(a)(c)(b)\1\4"
+        );
+        let conversion = convert(r"(?:a)(?<n>b)\1", Flavor::Pcre, Flavor::Posix);
+        assert_eq!(conversion.pattern, r"(a)(b)\2");
+    }
+
+    #[test]
+    fn lookaround_is_left_for_the_model() {
+        let conversion = convert(r"(?<=\$)\d+", Flavor::Pcre, Flavor::Rust);
+        assert_eq!(conversion.pattern, r"(?<=\$)\d+");
+        assert!(!conversion.possible);
+        assert_eq!(conversion.remaining[0].construct, Construct::Lookbehind);
+    }
+
+    #[test]
+    fn converts_between_basic_and_extended_flavors() {
+        let conversion = convert(r"(\d{3})-\1", Flavor::Pcre, Flavor::Bre);
+        assert_eq!(conversion.pattern, r"\([[:digit:]]\{3\}\)-\1");
+        assert!(conversion.possible);
+        let conversion = convert(r"^\([[:digit:]]*\)+$", Flavor::Bre, Flavor::Python);
+        assert_eq!(conversion.pattern, r"^([0-9]*)\+$");
+        assert!(conversion.possible);
+        assert!(!conversion.lossy);
+        assert!(!convert("cat|dog", Flavor::Posix, Flavor::Bre).possible);
+        assert!(!convert(r"a\+", Flavor::Bre, Flavor::Posix).possible);
+    }
+
+    #[test]
+    fn settle_rejects_uncompilable_result() {
+        let mut conversion = convert("a", Flavor::Pcre, Flavor::Rust);
+        conversion.settle("(a".to_string());
+        assert!(!conversion.possible);
+        assert!(conversion.notes[0].contains("does not compile with regex"));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::config::xdg_dir;
+use crate::error::Result;
+use crate::flavor::Flavor;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The last generate conversation in a directory, kept so `rgx refine` can
+/// resume it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Session {
+    pub session_id: String,
+    pub backend: String,
+    pub model: String,
+    pub flavor: Flavor,
+    pub description: String,
+    pub pattern: String,
+}
+
+/// Sessions keyed by working directory, stored in a single JSON file.
+pub struct SessionStore {
+    path: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(path: PathBuf) -> Self {
+        SessionStore { path }
+    }
+
+    /// `$XDG_STATE_HOME/rgx/sessions.json`, or under `~/.local/state`.
+    pub fn default_path() -> Option<PathBuf> {
+        xdg_dir("XDG_STATE_HOME", ".local/state").map(|dir| dir.join("sessions.json"))
+    }
+
+    fn load_all(&self) -> Result<BTreeMap<String, Session>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(&self.path)?)?)
+    }
+
+    pub fn get(&self, dir: &Path) -> Result<Option<Session>> {
+        Ok(self.load_all()?.remove(&dir.to_string_lossy().into_owned()))
+    }
+
+    pub fn set(&self, dir: &Path, session: Session) -> Result<()> {
+        // An unreadable store is replaced rather than blocking new sessions
+        let mut sessions = self.load_all().unwrap_or_default();
+        sessions.insert(dir.to_string_lossy().into_owned(), session);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&sessions)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_session(id: &str) -> Session {
+        Session {
+            session_id: id.to_string(),
+            backend: "claude".to_string(),
+            model: "haiku".to_string(),
+            flavor: Flavor::Rust,
+            description: "email".to_string(),
+            pattern: r"\S+@\S+".to_string(),
+        }
+    }
+
+    #[test]
+    fn missing_store_has_no_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().join("sessions.json"));
+        assert!(store.get(Path::new("/work")).unwrap().is_none());
+    }
+
+    #[test]
+    fn sessions_are_per_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().join("state").join("sessions.json"));
+        store.set(Path::new("/a"), make_session("one")).unwrap();
+        store.set(Path::new("/b"), make_session("two")).unwrap();
+
+        assert_eq!(
+            store.get(Path::new("/a")).unwrap().unwrap().session_id,
+            "one"
+        );
+        assert_eq!(
+            store.get(Path::new("/b")).unwrap().unwrap().session_id,
+            "two"
+        );
+        assert!(store.get(Path::new("/c")).unwrap().is_none());
+    }
+
+    #[test]
+    fn set_replaces_previous_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path().join("sessions.json"));
+        store.set(Path::new("/a"), make_session("one")).unwrap();
+        store.set(Path::new("/a"), make_session("two")).unwrap();
+        assert_eq!(
+            store.get(Path::new("/a")).unwrap().unwrap().session_id,
+            "two"
+        );
+    }
+
+    #[test]
+    fn corrupt_store_is_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sessions.json");
+        fs::write(&path, "not json").unwrap();
+        let store = SessionStore::new(path);
+        assert!(store.get(Path::new("/a")).is_err());
+        store.set(Path::new("/a"), make_session("one")).unwrap();
+        assert!(store.get(Path::new("/a")).unwrap().is_some());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::backend::{Backend, Metadata, Reply};
+use crate::error::Result;
+use crossterm::cursor::MoveToColumn;
+use crossterm::style::Print;
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::QueueableCommand;
+use serde_json::Value;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const TICK: Duration = Duration::from_millis(100);
+
+/// Whether a spinner line is currently on screen.
+static DRAWN: AtomicBool = AtomicBool::new(false);
+
+/// One line of the spinner: a frame, what we are waiting for, and how long.
+fn frame(tick: usize, label: &str, elapsed: Duration) -> String {
+    format!(
+        "{} {} {:.1}s",
+        FRAMES[tick % FRAMES.len()],
+        label,
+        elapsed.as_secs_f64()
+    )
+}
+
+fn draw(line: &str) {
+    let mut stderr = io::stderr();
+    let _ = stderr
+        .queue(MoveToColumn(0))
+        .and_then(|s| s.queue(Clear(ClearType::CurrentLine)))
+        .and_then(|s| s.queue(Print(line)));
+    let _ = stderr.flush();
+    DRAWN.store(true, Ordering::SeqCst);
+}
+
+/// Erase the spinner line if one is showing. Also called on Ctrl-C so an
+/// interrupted run leaves a clean terminal.
+pub fn clear() {
+    if DRAWN.swap(false, Ordering::SeqCst) {
+        let mut stderr = io::stderr();
+        let _ = stderr
+            .queue(MoveToColumn(0))
+            .and_then(|s| s.queue(Clear(ClearType::CurrentLine)));
+        let _ = stderr.flush();
+    }
+}
+
+/// An animation running on its own thread until dropped.
+struct Spin {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Spin {
+    fn start(label: String) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let start = Instant::now();
+            let mut tick = 0;
+            // Nothing is drawn for replies that arrive within the first tick
+            loop {
+                thread::park_timeout(TICK);
+                if flag.load(Ordering::SeqCst) {
+                    break;
+                }
+                draw(&frame(tick, &label, start.elapsed()));
+                tick += 1;
+            }
+            clear();
+        });
+        Spin {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Spin {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Backend wrapper that animates a spinner with the elapsed time on stderr
+/// while each call is in flight. Only meant for an interactive stderr.
+pub struct Spinner<B> {
+    inner: B,
+}
+
+impl<B: Backend> Spinner<B> {
+    pub fn new(inner: B) -> Self {
+        Spinner { inner }
+    }
+
+    fn spin<T>(&self, call: impl FnOnce() -> T) -> T {
+        let label = format!("Waiting for {} ({})", self.inner.name(), self.inner.model());
+        let _spin = Spin::start(label);
+        call()
+    }
+}
+
+impl<B: Backend> Backend for Spinner<B> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn identity(&self) -> String {
+        self.inner.identity()
+    }
+
+    fn query(&self, prompt: &str) -> Result<Reply> {
+        self.spin(|| self.inner.query(prompt))
+    }
+
+    fn resume(&self, session_id: &str, prompt: &str) -> Result<Reply> {
+        self.spin(|| self.inner.resume(session_id, prompt))
+    }
+
+    fn query_structured(&self, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.spin(|| self.inner.query_structured(prompt, schema))
+    }
+
+    fn resume_structured(&self, session_id: &str, prompt: &str, schema: &Value) -> Result<Reply> {
+        self.spin(|| self.inner.resume_structured(session_id, prompt, schema))
+    }
+
+    fn discard(&self, prompt: &str, schema: &Value) {
+        self.inner.discard(prompt, schema)
+    }
+
+    fn metadata(&self) -> Metadata {
+        self.inner.metadata()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::mock::MockBackend;
+
+    #[test]
+    fn frame_shows_label_and_elapsed() {
+        assert_eq!(
+            frame(0, "Waiting for claude (haiku)", Duration::from_millis(2345)),
+            "⠋ Waiting for claude (haiku) 2.3s"
+        );
+    }
+
+    #[test]
+    fn frames_cycle() {
+        let first = frame(0, "x", Duration::ZERO);
+        assert_eq!(frame(FRAMES.len(), "x", Duration::ZERO), first);
+        assert_ne!(frame(1, "x", Duration::ZERO), first);
+    }
+
+    #[test]
+    fn forwards_calls_and_errors() {
+        let backend = Spinner::new(MockBackend::new(["ok"]).with_session("s"));
+        assert_eq!(backend.name(), "mock");
+        assert_eq!(backend.query("prompt").unwrap().text, "ok");
+        assert!(backend.resume("s", "again").is_err());
+        assert_eq!(backend.inner.prompts(), vec!["prompt", "again"]);
+    }
+}
@@ -0,0 +1,166 @@
+//! Conversion between POSIX basic and extended syntax, which differ only
+//! in which of `( ) { } + ? |` need a backslash to be special.
+//!
+//! The engine, the inference and the flavor rewrites all work on extended
+//! syntax and pass basic patterns through these two functions.
+
+/// Index of the `]` closing the POSIX bracket expression that opens at
+/// `start`, in which a backslash is an ordinary member.
+pub fn bracket_end(chars: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    if chars.get(i) == Some(&'^') {
+        i += 1;
+    }
+    // A leading ] is a literal member
+    if chars.get(i) == Some(&']') {
+        i += 1;
+    }
+    while i < chars.len() {
+        match chars[i] {
+            ']' => return Some(i),
+            '[' if matches!(chars.get(i + 1), Some(':' | '=' | '.')) => {
+                let delimiter = chars[i + 1];
+                i += 2;
+                while i + 1 < chars.len() && !(chars[i] == delimiter && chars[i + 1] == ']') {
+                    i += 1;
+                }
+                i += 2;
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// `pattern` in POSIX basic syntax rewritten in extended syntax. GNU's
+/// `\+`, `\?` and `\|` are not POSIX and are refused.
+pub fn basic_to_extended(pattern: &str) -> std::result::Result<String, String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::with_capacity(pattern.len());
+    // * is an ordinary character at the start of the pattern or a group
+    let mut at_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = at_start;
+        at_start = false;
+        match c {
+            '\\' => {
+                match chars.get(i + 1) {
+                    Some('(') => {
+                        out.push('(');
+                        at_start = true;
+                    }
+                    Some(&close @ (')' | '{' | '}')) => out.push(close),
+                    Some(&gnu @ ('+' | '?' | '|')) => {
+                        return Err(format!(
+                            "\\{} at offset {} is a GNU extension, not POSIX BRE",
+                            gnu, i
+                        ))
+                    }
+                    Some(&escaped) => {
+                        out.push('\\');
+                        out.push(escaped);
+                    }
+                    None => out.push('\\'),
+                }
+                i += 1;
+            }
+            '(' | ')' | '{' | '}' | '+' | '?' | '|' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '*' if start => out.push_str(r"\*"),
+            '^' if start => {
+                out.push('^');
+                at_start = true;
+            }
+            '^' => out.push_str(r"\^"),
+            '$' if i + 1 == chars.len() || chars[i + 1..].starts_with(&['\\', ')']) => {
+                out.push('$')
+            }
+            '$' => out.push_str(r"\$"),
+            '[' => {
+                let end = bracket_end(&chars, i).unwrap_or(chars.len() - 1);
+                out.extend(&chars[i..=end]);
+                i = end;
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+    Ok(out)
+}
+
+/// `pattern` in extended syntax rewritten in POSIX basic syntax. `|` has no
+/// basic equivalent and is left for the check to report.
+pub fn extended_to_basic(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::with_capacity(pattern.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' => {
+                match chars.get(i + 1) {
+                    // Escaped to be literal in extended syntax, literal as is in basic
+                    Some(&literal @ ('(' | ')' | '{' | '}' | '+' | '?' | '|')) => out.push(literal),
+                    Some(&escaped) => {
+                        out.push('\\');
+                        out.push(escaped);
+                    }
+                    None => out.push('\\'),
+                }
+                i += 1;
+            }
+            '(' | ')' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '{' => match chars[i..].iter().position(|&c| c == '}') {
+                Some(len)
+                    if chars[i + 1..i + len]
+                        .iter()
+                        .all(|c| c.is_ascii_digit() || *c == ',') =>
+                {
+                    let bounds: String = chars[i + 1..i + len].iter().collect();
+                    out.push_str(&format!(r"\{{{}\}}", bounds));
+                    i += len;
+                }
+                _ => out.push('{'),
+            },
+            '+' => out.push_str(r"\{1,\}"),
+            '?' => out.push_str(r"\{0,1\}"),
+            '[' => {
+                let end = bracket_end(&chars, i).unwrap_or(chars.len() - 1);
+                out.extend(&chars[i..=end]);
+                i = end;
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_syntax_round_trips_through_extended() {
+        assert_eq!(
+            basic_to_extended(r"^*\(a+\)\{2\}[(]?$x\1").unwrap(),
+            r"^\*(a\+){2}[(]\?\$x\1"
+        );
+        assert!(basic_to_extended(r"a\|b")
+            .unwrap_err()
+            .contains("GNU extension"));
+        assert_eq!(
+            extended_to_basic(r"(a+|b?){2,}\+[+]"),
+            r"\(a\{1,\}|b\{0,1\}\)\{2,\}+[+]"
+        );
+    }
+}
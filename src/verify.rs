@@ -0,0 +1,247 @@
+use crate::commands::generate::GenerateResponse;
+use crate::engine::{self, Engine};
+use crate::error::Error;
+use crate::flavor::Flavor;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+/// Outcome of testing one example string against a pattern.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ExampleCheck {
+    pub example: String,
+    /// Whether the example is supposed to match
+    pub expected: bool,
+    /// Whether the pattern actually matches it
+    pub actual: bool,
+}
+
+impl ExampleCheck {
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+/// Result of checking a generated pattern against its own examples locally.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Verification {
+    /// Local engine the pattern was checked with
+    pub engine: &'static str,
+    /// Compile error, if the pattern could not be compiled
+    pub error: Option<String>,
+    /// Whether `error` is the engine lacking a feature the flavor has,
+    /// which says nothing about the pattern
+    pub unsupported: bool,
+    pub matches: Vec<ExampleCheck>,
+    pub non_matches: Vec<ExampleCheck>,
+}
+
+impl Verification {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.failures().next().is_none()
+    }
+
+    /// Whether verification found something wrong with the pattern, as
+    /// opposed to passing or being beyond the engine.
+    pub fn failed(&self) -> bool {
+        !self.passed() && !self.unsupported
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &ExampleCheck> {
+        self.matches
+            .iter()
+            .chain(&self.non_matches)
+            .filter(|check| !check.passed())
+    }
+
+    /// Human-readable list of everything that went wrong, one item per line.
+    pub fn describe_failures(&self) -> String {
+        if let Some(error) = &self.error {
+            return format!("- The pattern failed to compile: {}", error);
+        }
+        self.failures()
+            .map(|check| {
+                if check.expected {
+                    format!(
+                        "- {:?} should match, but the pattern does not match it",
+                        check.example
+                    )
+                } else {
+                    format!(
+                        "- {:?} should NOT match, but the pattern matches it",
+                        check.example
+                    )
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Serialize for Verification {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Verification", 6)?;
+        state.serialize_field("passed", &self.passed())?;
+        state.serialize_field("engine", &self.engine)?;
+        state.serialize_field("error", &self.error)?;
+        state.serialize_field("unsupported", &self.unsupported)?;
+        state.serialize_field("matches", &self.matches)?;
+        state.serialize_field("non_matches", &self.non_matches)?;
+        state.end()
+    }
+}
+
+/// Compile `resp.pattern` with the local engine for `flavor` and test every
+/// example.
+pub fn verify(resp: &GenerateResponse, flavor: Flavor) -> Verification {
+    let regex = match Engine::compile(&resp.pattern, flavor) {
+        Ok(regex) => regex,
+        Err(e) => {
+            let unsupported = matches!(e, Error::UnsupportedRegex { .. });
+            let message = match e {
+                Error::InvalidRegex { message, .. } | Error::UnsupportedRegex { message, .. } => {
+                    message
+                }
+                other => other.to_string(),
+            };
+            return Verification {
+                engine: engine::name(flavor),
+                error: Some(message),
+                unsupported,
+                ..Verification::default()
+            };
+        }
+    };
+
+    let check = |examples: &[String], expected: bool| {
+        examples
+            .iter()
+            .map(|example| ExampleCheck {
+                example: example.clone(),
+                expected,
+                actual: regex.is_match(example),
+            })
+            .collect()
+    };
+
+    Verification {
+        engine: regex.name(),
+        error: None,
+        unsupported: false,
+        matches: check(&resp.matches, true),
+        non_matches: check(&resp.non_matches, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_generated(pattern: &str, matches: &[&str], non_matches: &[&str]) -> GenerateResponse {
+        GenerateResponse {
+            pattern: pattern.to_string(),
+            matches: matches.iter().map(|s| s.to_string()).collect(),
+            non_matches: non_matches.iter().map(|s| s.to_string()).collect(),
+            explanation: String::new(),
+        }
+    }
+
+    #[test]
+    fn all_examples_pass() {
+        let v = verify(
+            &make_generated(r"^\d+$", &["123", "4"], &["abc", "12a"]),
+            Flavor::Rust,
+        );
+        assert!(v.passed());
+        assert_eq!(v.matches.len(), 2);
+        assert_eq!(v.non_matches.len(), 2);
+        assert_eq!(v.failures().count(), 0);
+    }
+
+    #[test]
+    fn misclassified_match_reported() {
+        let v = verify(
+            &make_generated(r"^\d+$", &["123", "12a"], &[]),
+            Flavor::Rust,
+        );
+        assert!(!v.passed());
+        let failures: Vec<_> = v.failures().collect();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].example, "12a");
+        assert!(failures[0].expected);
+        assert!(!failures[0].actual);
+    }
+
+    #[test]
+    fn misclassified_non_match_reported() {
+        let v = verify(&make_generated(r"\d+", &[], &["abc1"]), Flavor::Rust);
+        assert!(!v.passed());
+        assert!(v.describe_failures().contains(r#""abc1" should NOT match"#));
+    }
+
+    #[test]
+    fn compile_error_fails_verification() {
+        let v = verify(&make_generated(r"(\d+", &["1"], &[]), Flavor::Rust);
+        assert!(!v.passed());
+        assert!(v.error.is_some());
+        assert!(v.matches.is_empty());
+        assert!(v.describe_failures().contains("failed to compile"));
+    }
+
+    #[test]
+    fn no_examples_passes() {
+        assert!(verify(&make_generated(r"x", &[], &[]), Flavor::Rust).passed());
+    }
+
+    #[test]
+    fn serializes_passed_flag() {
+        let v = verify(&make_generated(r"^\d+$", &["1"], &["1"]), Flavor::Rust);
+        let json = serde_json::to_value(&v).unwrap();
+        assert_eq!(json["passed"], false);
+        assert_eq!(json["engine"], "regex");
+        assert!(json["error"].is_null());
+        assert_eq!(json["matches"][0]["example"], "1");
+        assert_eq!(json["non_matches"][0]["expected"], false);
+        assert_eq!(json["non_matches"][0]["actual"], true);
+    }
+
+    #[test]
+    fn verifies_with_the_flavor_engine() {
+        let resp = make_generated(r"(?<=\$)\d+", &["$5"], &["5"]);
+        let v = verify(&resp, Flavor::Pcre);
+        assert!(v.passed());
+        assert_eq!(v.engine, "fancy-regex");
+
+        let v = verify(&resp, Flavor::Rust);
+        assert!(v.error.is_some());
+        assert_eq!(v.engine, "regex");
+
+        let v = verify(&make_generated(r"^\d+$", &["1"], &[]), Flavor::Posix);
+        assert!(v.describe_failures().contains("not POSIX ERE"));
+        assert_eq!(v.engine, "posix-ere");
+    }
+
+    #[test]
+    fn unsupported_constructs_fail_verification() {
+        let v = verify(&make_generated(r"\p{L}+", &["a"], &[]), Flavor::Python);
+        assert!(!v.passed());
+        assert!(v.describe_failures().contains("not supported by Python re"));
+    }
+
+    #[test]
+    fn engine_gaps_are_not_failures() {
+        let v = verify(&make_generated(r"^[^]$", &["a"], &[]), Flavor::Js);
+        assert!(!v.passed());
+        assert!(v.unsupported);
+        assert!(!v.failed());
+
+        let v = verify(&make_generated(r"(a", &["a"], &[]), Flavor::Js);
+        assert!(!v.unsupported);
+        assert!(v.failed());
+    }
+
+    #[test]
+    fn describe_lists_every_failure() {
+        let v = verify(&make_generated(r"^a$", &["b", "c"], &["a"]), Flavor::Rust);
+        assert_eq!(v.describe_failures().lines().count(), 3);
+    }
+}